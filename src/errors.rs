@@ -2,7 +2,7 @@
 use alloc::alloc;
 #[cfg(not(target_env = "sgx"))]
 use std::alloc;
-use std::num;
+use std::{io, num};
 
 use ndarray;
 use serde_json;
@@ -14,6 +14,11 @@ error_chain! {
       display("invalid downcast: expected `{}` but was `{}`", expected, actual)
     }
 
+    TryFromTVMArgValueError(expected: String, actual: i64) {
+      description("mismatched types while converting TVMArgValue")
+      display("invalid argument: expected `{}` but type code was `{}`", expected, actual)
+    }
+
     GraphFormatError(msg: String) {
       description("unable to load graph")
       display("could not load graph json: {}", msg)
@@ -23,10 +28,36 @@ error_chain! {
       description("unable to load graph params")
       display("could not load graph params: {}", msg)
     }
+
+    LoadTensorError(msg: String) {
+      description("unable to load tensor")
+      display("could not load tensor: {}", msg)
+    }
+
+    LoadModelBundleError(msg: String) {
+      description("unable to load model bundle")
+      display("could not load model bundle: {}", msg)
+    }
+
+    LoadModuleError(msg: String) {
+      description("unable to load module")
+      display("could not load module: {}", msg)
+    }
+
+    FunctionCallError(msg: String) {
+      description("function call failed")
+      display("function call failed: {}", msg)
+    }
+
+    OpExecError(node_name: String, func_name: String, msg: String) {
+      description("op failed")
+      display("op `{}` (function `{}`) failed: {}", node_name, func_name, msg)
+    }
   }
   foreign_links {
     Alloc(alloc::AllocErr);
     GraphDeserialize(serde_json::Error);
+    Io(io::Error);
     ParseInt(num::ParseIntError);
     ShapeError(ndarray::ShapeError);
   }
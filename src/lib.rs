@@ -31,6 +31,10 @@ extern crate error_chain;
 extern crate itertools;
 #[macro_use]
 extern crate lazy_static;
+#[cfg(unix)]
+extern crate libc;
+#[cfg(not(any(target_arch = "wasm32", target_env = "sgx")))]
+extern crate memmap;
 extern crate ndarray;
 #[macro_use]
 extern crate nom;
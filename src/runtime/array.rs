@@ -1,18 +1,20 @@
 use std::{
   any::TypeId,
+  cmp,
   convert::TryFrom,
-  mem,
+  fmt, mem,
   os::raw::{c_int, c_void},
-  ptr, slice,
+  ptr, slice, str,
 };
 
 use ndarray;
+use serde;
 
-use super::allocator::Allocation;
+use super::{allocator::Allocation, get_global, graph::num_elements, TVMArgValue, TypedPackedFunc};
 use errors::*;
 use ffi::runtime::{
   DLContext, DLDataType, DLDataTypeCode_kDLFloat, DLDataTypeCode_kDLInt, DLDataTypeCode_kDLUInt,
-  DLDeviceType_kDLCPU, DLTensor,
+  DLDeviceType_kDLCPU, DLTensor, TVMTypeCode_kArrayHandle, TVMTypeCode_kHandle,
 };
 
 /// A `Storage` is a container which holds `Tensor` data.
@@ -111,7 +113,7 @@ impl<'a, T> From<&'a [T]> for Storage<'a> {
 /// let mut a_nd: ndarray::Array = ndarray::Array::from_vec(vec![1f32, 2., 3., 4.]);
 /// let mut a: Tensor = a_nd.into();
 /// let mut a_dl: DLTensor = (&mut t).into();
-/// call_packed!(tvm_fn, &mut a_dl);
+/// call_packed!(tvm_fn, &mut a_dl).unwrap();
 ///
 /// // Array -> Tensor is mostly useful when post-processing TVM graph outputs.
 /// let mut a_nd = ndarray::Array::try_from(&a).unwrap();
@@ -306,6 +308,109 @@ impl<'a, 't> From<&'a mut Tensor<'t>> for DLTensor {
   }
 }
 
+impl Tensor<'static> {
+  /// Creates a `Tensor` from a `DLTensor` argument, such as one passed to a packed function
+  /// implemented in Rust. The `Tensor` owns a copy of the argument's data rather than viewing
+  /// it, since the caller may hold the data only by shared reference.
+  ///
+  /// # Safety
+  ///
+  /// If `arg` is an array handle, it must point to a valid `DLTensor` whose shape, strides,
+  /// and data, where not null, can be read.
+  pub(super) unsafe fn from_arg(arg: TVMArgValue) -> Result<Tensor<'static>> {
+    ensure!(
+      arg.type_code == TVMTypeCode_kArrayHandle as i64,
+      ErrorKind::TryFromTVMArgValueError("Tensor".to_string(), arg.type_code)
+    );
+    let dl_tensor = &*(arg.value.v_handle as *const DLTensor);
+    ensure!(
+      dl_tensor.ctx.device_type == DLDeviceType_kDLCPU,
+      "Unsupported device type {}",
+      dl_tensor.ctx.device_type
+    );
+    ensure!(
+      dl_tensor.ndim >= 0,
+      "Invalid number of dimensions {}",
+      dl_tensor.ndim
+    );
+    let ndim = dl_tensor.ndim as usize;
+    // frontends pass a null shape for scalars
+    let shape = if ndim == 0 {
+      Vec::new()
+    } else {
+      ensure!(
+        !dl_tensor.shape.is_null(),
+        "The shape of a tensor of {} dimensions is null",
+        ndim
+      );
+      slice::from_raw_parts(dl_tensor.shape, ndim).to_vec()
+    };
+    let size = match num_elements(&shape) {
+      Some(size) => size,
+      None => bail!("Invalid shape {:?}", shape),
+    };
+    let strides = if dl_tensor.strides.is_null() || ndim == 0 {
+      None
+    } else {
+      let strides = slice::from_raw_parts(dl_tensor.strides, ndim);
+      ensure!(
+        strides.iter().all(|&stride| stride >= 0),
+        "Negative strides {:?} are not supported",
+        strides
+      );
+      Some(
+        strides
+          .iter()
+          .map(|&stride| stride as usize)
+          .collect::<Vec<usize>>(),
+      )
+    };
+    let dtype = DataType {
+      code: dl_tensor.dtype.code as usize,
+      bits: dl_tensor.dtype.bits as usize,
+      lanes: dl_tensor.dtype.lanes as usize,
+    };
+    // the number of elements spanned by the data, which may be strided
+    let extent = match strides {
+      Some(ref strides) if size > 0 => {
+        shape
+          .iter()
+          .zip(strides)
+          .try_fold(1usize, |extent, (&dim, &stride)| {
+            (dim as usize - 1)
+              .checked_mul(stride)
+              .and_then(|span| extent.checked_add(span))
+          })
+      }
+      _ => Some(size),
+    };
+    let num_bytes = match extent.and_then(|extent| extent.checked_mul(dtype.itemsize())) {
+      Some(num_bytes) => num_bytes,
+      None => bail!("Strides {:?} span too much data", strides),
+    };
+    let data = Storage::new(num_bytes, Some(cmp::max(dtype.itemsize(), 1)))?;
+    if num_bytes > 0 {
+      ensure!(!dl_tensor.data.is_null(), "The data of a tensor is null");
+      data.as_mut_ptr().copy_from_nonoverlapping(
+        (dl_tensor.data as *const u8).offset(dl_tensor.byte_offset as isize),
+        num_bytes,
+      );
+    }
+    Ok(Tensor {
+      data: data,
+      ctx: TVMContext {
+        device_type: dl_tensor.ctx.device_type as usize,
+        device_id: dl_tensor.ctx.device_id as usize,
+      },
+      dtype: dtype,
+      size: size,
+      shape: shape,
+      strides: strides,
+      byte_offset: 0,
+    })
+  }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct DataType {
   pub(super) code: usize,
@@ -314,9 +419,30 @@ pub struct DataType {
 }
 
 impl DataType {
+  /// Returns the `DLDataTypeCode` of this `DataType`.
+  pub fn code(&self) -> usize {
+    self.code
+  }
+
+  /// Returns the number of bits in each lane of this `DataType`.
+  pub fn bits(&self) -> usize {
+    self.bits
+  }
+
+  /// Returns the number of lanes in this `DataType`.
+  pub fn lanes(&self) -> usize {
+    self.lanes
+  }
+
   /// Returns the number of bytes occupied by an element of this `DataType`.
-  fn itemsize(&self) -> usize {
-    (self.bits * self.lanes) >> 3
+  pub(super) fn itemsize(&self) -> usize {
+    (self.bits * self.lanes + 7) >> 3
+  }
+
+  /// Returns the alignment in bytes required by an element of this `DataType`: its size
+  /// rounded up to a power of two.
+  pub(super) fn align(&self) -> usize {
+    cmp::max(self.itemsize(), 1).next_power_of_two()
   }
 
   /// Returns whether this `DataType` represents primitive type `T`.
@@ -332,6 +458,146 @@ impl DataType {
       || (typ == TypeId::of::<f32>() && self.code == 2 && self.bits == 32)
       || (typ == TypeId::of::<f64>() && self.code == 2 && self.bits == 64)
   }
+
+  /// Returns whether this `DataType` has a type code which TVM can produce, such as one parsed
+  /// by `DataType::from_str`, and a non-zero size.
+  pub(super) fn is_valid(&self) -> bool {
+    let known_code = [
+      DLDataTypeCode_kDLInt as usize,
+      DLDataTypeCode_kDLUInt as usize,
+      DLDataTypeCode_kDLFloat as usize,
+      TVMTypeCode_kHandle as usize,
+      DTYPE_CODE_BFLOAT,
+    ].contains(&self.code)
+      || self.code >= DTYPE_CODE_CUSTOM_BEGIN;
+    known_code && self.bits > 0 && self.lanes > 0
+  }
+}
+
+// @see `kDLBfloat` in dlpack.h
+const DTYPE_CODE_BFLOAT: usize = 4;
+// @see `kCustomBegin` in packed_func.h
+const DTYPE_CODE_CUSTOM_BEGIN: usize = 129;
+
+impl str::FromStr for DataType {
+  type Err = Error;
+
+  /// Parses a TVM type string such as `"float32"`, `"int8x4"`, `"bool"`, `"handle"` or
+  /// `"custom[posit]16"`. A custom type is given by its type code or by a name which the global
+  /// function `_datatype_get_type_code`, if registered, resolves to a code, as in TVM.
+  /// @see `String2TVMType` in packed_func.h
+  fn from_str(type_str: &str) -> Result<Self> {
+    let invalid =
+      || -> Error { ErrorKind::GraphFormatError(format!("Invalid dltype: {}", type_str)).into() };
+
+    // a custom type name may contain `x`, so the lanes are only split from what follows it
+    let (custom_name, rest) = if type_str.starts_with("custom[") {
+      let end = type_str.find(']').ok_or_else(invalid)?;
+      (Some(&type_str[7..end]), &type_str[end + 1..])
+    } else {
+      (None, type_str)
+    };
+    let mut parts = rest.splitn(2, 'x');
+    let base = parts.next().unwrap();
+    let lanes = match parts.next() {
+      Some(lanes) => lanes.parse::<u16>().map_err(|_| invalid())? as usize,
+      None => 1,
+    };
+
+    let (code, default_bits, bits) = if let Some(type_name) = custom_name {
+      let code = match type_name.parse::<u8>() {
+        Ok(code) => code as usize,
+        Err(_) => custom_type_code(type_name).ok_or_else(|| -> Error {
+          ErrorKind::GraphFormatError(format!(
+            "Invalid dltype: {} (custom type `{}` is not registered)",
+            type_str, type_name
+          )).into()
+        })?,
+      };
+      ensure!(code >= DTYPE_CODE_CUSTOM_BEGIN, invalid());
+      (code, 32, base)
+    } else {
+      let (name, bits) = base.split_at(
+        base
+          .find(|c: char| c.is_ascii_digit())
+          .unwrap_or(base.len()),
+      );
+      let (code, default_bits) = match name {
+        "int" => (DLDataTypeCode_kDLInt as usize, 32),
+        "uint" => (DLDataTypeCode_kDLUInt as usize, 32),
+        "float" => (DLDataTypeCode_kDLFloat as usize, 32),
+        "bfloat" => (DTYPE_CODE_BFLOAT, 16),
+        "handle" => (TVMTypeCode_kHandle as usize, 64),
+        "bool" if bits.is_empty() => (DLDataTypeCode_kDLUInt as usize, 1),
+        _ => bail!(invalid()),
+      };
+      (code, default_bits, bits)
+    };
+    let bits = if bits.is_empty() {
+      default_bits
+    } else {
+      bits.parse::<u8>().map_err(|_| invalid())? as usize
+    };
+    ensure!(bits > 0 && lanes > 0, invalid());
+
+    Ok(DataType {
+      code: code,
+      bits: bits,
+      lanes: lanes,
+    })
+  }
+}
+
+/// Returns the code of the custom type registered as `type_name`.
+/// @see `GetCustomTypeCode` in packed_func.h
+fn custom_type_code(type_name: &str) -> Option<usize> {
+  let get_type_code = get_global("_datatype_get_type_code")?;
+  TypedPackedFunc::<(&str,), i64>::new(get_type_code)
+    .call((type_name,))
+    .ok()
+    .filter(|&code| code >= 0 && code <= u8::max_value() as i64)
+    .map(|code| code as usize)
+}
+
+impl fmt::Display for DataType {
+  /// Formats this `DataType` as a TVM type string. Custom types are formatted using their code.
+  /// @see `TVMType2String` in packed_func.h
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.code == DLDataTypeCode_kDLUInt as usize && self.bits == 1 && self.lanes == 1 {
+      return write!(f, "bool");
+    }
+    match self.code {
+      code if code == DLDataTypeCode_kDLInt as usize => write!(f, "int{}", self.bits)?,
+      code if code == DLDataTypeCode_kDLUInt as usize => write!(f, "uint{}", self.bits)?,
+      code if code == DLDataTypeCode_kDLFloat as usize => write!(f, "float{}", self.bits)?,
+      DTYPE_CODE_BFLOAT => write!(f, "bfloat{}", self.bits)?,
+      code if code == TVMTypeCode_kHandle as usize => write!(f, "handle")?,
+      code => write!(f, "custom[{}]{}", code, self.bits)?,
+    }
+    if self.lanes != 1 {
+      write!(f, "x{}", self.lanes)?;
+    }
+    Ok(())
+  }
+}
+
+impl serde::Serialize for DataType {
+  fn serialize<S: serde::Serializer>(
+    &self,
+    serializer: S,
+  ) -> ::std::result::Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+impl<'de> serde::Deserialize<'de> for DataType {
+  fn deserialize<D: serde::Deserializer<'de>>(
+    deserializer: D,
+  ) -> ::std::result::Result<Self, D::Error> {
+    String::deserialize(deserializer)?
+      .parse()
+      .map_err(|err: Error| serde::de::Error::custom(err.to_string()))
+  }
 }
 
 impl<'a> From<&'a DataType> for DLDataType {
@@ -0,0 +1,259 @@
+use std::{collections::HashMap, convert::TryFrom, str};
+
+use nom::le_u64;
+use serde_json;
+
+use super::{graph::put_le, load_param_dict, Graph, GraphExecutor, Module, Tensor};
+use errors::{ErrorKind, Result};
+
+/// "TVMBUNDL" in little-endian byte order.
+const BUNDLE_MAGIC: u64 = 0x4c44_4e55_424d_5654;
+const BUNDLE_FORMAT_VERSION: u64 = 1;
+
+/// Describes the model in a `ModelBundle`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BundleMetadata {
+  /// The version of the model, as chosen by whoever created the bundle.
+  pub model_version: String,
+  /// The names of the graph inputs which are not set from the bundled params.
+  pub input_names: Vec<String>,
+  /// The FNV-1a hash of the graph JSON and param dict, which is checked on load.
+  pub hash: u64,
+}
+
+/// A graph, its params, and metadata stored as a single artifact so that a model can be
+/// embedded using `include_bytes!`.
+///
+/// A bundle consists of the magic number and format version, each as a little-endian `u64`,
+/// followed by the metadata JSON, the graph JSON, and the param dict, each prefixed by its
+/// length as a little-endian `u64`. The param dict is in the format of
+/// `nnvm.compiler.save_param_dict`.
+///
+/// # Examples
+///
+/// ```
+/// // when building the bundle
+/// let graph_json = fs::read_to_string("deploy_graph.json").unwrap();
+/// let params = fs::read("deploy_param.params").unwrap();
+/// let bundle = ModelBundle::new(&graph_json, &params, "1.0").unwrap();
+/// fs::write("model.bundle", bundle.to_bytes()).unwrap();
+///
+/// // when deploying the bundle
+/// static BUNDLE: &[u8] = include_bytes!("model.bundle");
+/// let bundle = ModelBundle::from_bytes(BUNDLE).unwrap();
+/// let mut exec = bundle.executor(&SystemLibModule::default()).unwrap();
+/// ```
+pub struct ModelBundle<'a> {
+  pub metadata: BundleMetadata,
+  graph_json: &'a str,
+  params: &'a [u8],
+}
+
+/// Parses the magic number, format version, metadata, graph, and params of a bundle.
+named!(
+  bundle_sections<&[u8], (u64, u64, &[u8], &[u8], &[u8])>,
+  tuple!(
+    le_u64,
+    le_u64,
+    length_bytes!(le_u64),
+    length_bytes!(le_u64),
+    length_bytes!(le_u64)
+  )
+);
+
+impl<'a> ModelBundle<'a> {
+  /// Creates a bundle from a graph JSON and a param dict saved using
+  /// `nnvm.compiler.save_param_dict`.
+  pub fn new<S: Into<String>>(
+    graph_json: &'a str,
+    params: &'a [u8],
+    model_version: S,
+  ) -> Result<Self> {
+    let graph = Graph::try_from(graph_json)?;
+    graph.validate()?;
+    let param_names = load_param_dict(params)?;
+    let input_names = graph
+      .arg_nodes
+      .iter()
+      .map(|&id| &graph.nodes[id].name)
+      .filter(|name| !param_names.contains_key(*name))
+      .cloned()
+      .collect();
+    Ok(ModelBundle {
+      metadata: BundleMetadata {
+        model_version: model_version.into(),
+        input_names: input_names,
+        hash: fnv1a(&[graph_json.as_bytes(), params]),
+      },
+      graph_json: graph_json,
+      params: params,
+    })
+  }
+
+  /// Parses a bundle written by `to_bytes`. The graph and params borrow from `bytes`.
+  pub fn from_bytes(bytes: &'a [u8]) -> Result<Self> {
+    let (remaining, (magic, version, metadata, graph_json, params)) = bundle_sections(bytes)
+      .map_err(|_| ErrorKind::LoadModelBundleError("truncated bundle".to_string()))?;
+    ensure!(
+      magic == BUNDLE_MAGIC,
+      ErrorKind::LoadModelBundleError(format!("invalid magic number {:#x}", magic))
+    );
+    ensure!(
+      version == BUNDLE_FORMAT_VERSION,
+      ErrorKind::LoadModelBundleError(format!("unsupported format version {}", version))
+    );
+    ensure!(
+      remaining.is_empty(),
+      ErrorKind::LoadModelBundleError("extra input".to_string())
+    );
+    let metadata: BundleMetadata = serde_json::from_slice(metadata)?;
+    ensure!(
+      fnv1a(&[graph_json, params]) == metadata.hash,
+      ErrorKind::LoadModelBundleError("hash does not match contents".to_string())
+    );
+    let graph_json = str::from_utf8(graph_json)
+      .map_err(|_| ErrorKind::LoadModelBundleError("graph is not valid UTF-8".to_string()))?;
+    Ok(ModelBundle {
+      metadata: metadata,
+      graph_json: graph_json,
+      params: params,
+    })
+  }
+
+  /// Serializes the bundle in the format read by `from_bytes`.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let metadata = serde_json::to_vec(&self.metadata).unwrap();
+    let mut buf =
+      Vec::with_capacity(40 + metadata.len() + self.graph_json.len() + self.params.len());
+    put_le(&mut buf, BUNDLE_MAGIC, 8);
+    put_le(&mut buf, BUNDLE_FORMAT_VERSION, 8);
+    for section in [&metadata[..], self.graph_json.as_bytes(), self.params].iter() {
+      put_le(&mut buf, section.len() as u64, 8);
+      buf.extend_from_slice(section);
+    }
+    buf
+  }
+
+  pub fn graph_json(&self) -> &'a str {
+    self.graph_json
+  }
+
+  pub fn graph(&self) -> Result<Graph> {
+    Graph::try_from(self.graph_json)
+  }
+
+  /// Returns the bundled params, which borrow their data from the bundle.
+  pub fn params(&self) -> Result<HashMap<String, Tensor<'a>>> {
+    load_param_dict(self.params)
+  }
+
+  /// Creates a `GraphExecutor` for the bundled graph as by `GraphExecutor::new_with_params`.
+  pub fn executor<'m, M: 'm + Module>(&self, lib: &'m M) -> Result<GraphExecutor<'m, 'a>> {
+    GraphExecutor::new_with_params(self.graph()?, lib, self.params()?)
+  }
+}
+
+/// Computes the 64-bit FNV-1a hash of the concatenation of `chunks`.
+fn fnv1a(chunks: &[&[u8]]) -> u64 {
+  let mut hash = 0xcbf2_9ce4_8422_2325u64;
+  for chunk in chunks.iter() {
+    for &byte in chunk.iter() {
+      hash ^= byte as u64;
+      hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+  }
+  hash
+}
+
+#[cfg(test)]
+mod tests {
+  use std::slice;
+
+  use ndarray::Array;
+
+  use super::*;
+  use runtime::{
+    graph::tests::{TestModule, TEST_GRAPH_JSON},
+    save_param_dict, Storage,
+  };
+
+  fn test_params() -> Vec<u8> {
+    let w = Array::from_shape_vec((2, 2), vec![1f32, 2., 3., 4.]).unwrap();
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), Tensor::from(&w));
+    save_param_dict(&params).unwrap()
+  }
+
+  #[test]
+  fn test_bundle_roundtrip() {
+    let params = test_params();
+    let bytes = ModelBundle::new(TEST_GRAPH_JSON, &params, "1.0")
+      .unwrap()
+      .to_bytes();
+    let bundle = ModelBundle::from_bytes(&bytes).unwrap();
+    assert_eq!(bundle.metadata.model_version, "1.0");
+    assert_eq!(bundle.metadata.input_names, vec!["x".to_string()]);
+    assert_eq!(bundle.graph_json(), TEST_GRAPH_JSON);
+
+    let mut exec = bundle.executor(&TestModule).unwrap();
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.run().unwrap();
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 5., 10., 17.]
+    );
+  }
+
+  #[test]
+  fn test_bundle_set_param() {
+    let params = test_params();
+    // the data of `w` ends the bundle, so pad the version such that the data is aligned as
+    // float32 requires for the executor to bind it
+    let bytes = (1..5)
+      .map(|len| {
+        ModelBundle::new(TEST_GRAPH_JSON, &params, "1".repeat(len))
+          .unwrap()
+          .to_bytes()
+      }).find(|bytes| (bytes.len() - 16) % 4 == 0)
+      .unwrap();
+    let storage = Storage::new(bytes.len(), Some(64)).unwrap();
+    let buf = unsafe { slice::from_raw_parts_mut(storage.as_mut_ptr(), bytes.len()) };
+    buf.copy_from_slice(&bytes);
+
+    let bundle = ModelBundle::from_bytes(buf).unwrap();
+    let mut exec = bundle.executor(&TestModule).unwrap();
+    assert_eq!(
+      exec.get_input("w").unwrap().data.as_ptr(),
+      buf[bytes.len() - 16..].as_ptr()
+    );
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    exec.set_input("w", Tensor::from(&x)).unwrap();
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.run().unwrap();
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 5., 10., 17.]
+    );
+    assert_eq!(&buf[..], &bytes[..]);
+  }
+
+  #[test]
+  fn test_bundle_invalid() {
+    let params = test_params();
+    let bytes = ModelBundle::new(TEST_GRAPH_JSON, &params, "1.0")
+      .unwrap()
+      .to_bytes();
+
+    let mut corrupted = bytes.clone();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 1;
+    let err = ModelBundle::from_bytes(&corrupted).err().unwrap();
+    assert!(err.to_string().contains("hash does not match"));
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] ^= 1;
+    assert!(ModelBundle::from_bytes(&bad_magic).is_err());
+    assert!(ModelBundle::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+  }
+}
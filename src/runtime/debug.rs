@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use super::{save_param_dict, Tensor};
+use errors::Result;
+
+/// Copies of the op outputs produced during `GraphExecutor::run_debug`.
+///
+/// Each output is keyed by `"<node name>:<output index>"`.
+///
+/// # Examples
+///
+/// ```
+/// let dump = exec.run_debug().unwrap();
+/// let fc1_out = dump.get("fuse_dense", 0).unwrap();
+/// fs::write("intermediates.params", dump.to_param_dict().unwrap()).unwrap();
+/// ```
+#[derive(Default)]
+pub struct DebugDump {
+  tensors: HashMap<String, Tensor<'static>>,
+  /// The keys of `tensors` in order of execution.
+  keys: Vec<String>,
+}
+
+impl DebugDump {
+  pub(super) fn record(&mut self, node_name: &str, index: usize, tensor: Tensor<'static>) {
+    let key = Self::key(node_name, index);
+    if self.tensors.insert(key.clone(), tensor).is_none() {
+      self.keys.push(key);
+    }
+  }
+
+  fn key(node_name: &str, index: usize) -> String {
+    format!("{}:{}", node_name, index)
+  }
+
+  /// Returns output `index` of the node named `node_name`, if it was recorded.
+  pub fn get<S: AsRef<str>>(&self, node_name: S, index: usize) -> Option<&Tensor<'static>> {
+    self.tensors.get(&Self::key(node_name.as_ref(), index))
+  }
+
+  /// Returns the recorded keys in order of execution.
+  pub fn keys(&self) -> &[String] {
+    &self.keys
+  }
+
+  pub fn len(&self) -> usize {
+    self.keys.len()
+  }
+
+  /// Serializes the recorded outputs in the format of `nnvm.compiler.save_param_dict`.
+  pub fn to_param_dict(&self) -> Result<Vec<u8>> {
+    save_param_dict(&self.tensors)
+  }
+
+  /// Returns the recorded outputs.
+  pub fn into_tensors(self) -> HashMap<String, Tensor<'static>> {
+    self.tensors
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::convert::TryFrom;
+
+  use ndarray::Array;
+  use serde_json;
+
+  use super::*;
+  use runtime::{
+    graph::tests::{TestModule, TEST_GRAPH_JSON},
+    load_param_dict, Graph, GraphExecutor,
+  };
+
+  #[test]
+  fn test_run_debug() {
+    // make `add_one` overwrite the output of `mul` so that the dump must hold a copy
+    let mut graph = Graph::try_from(TEST_GRAPH_JSON).unwrap();
+    graph.attrs.as_mut().unwrap().insert(
+      "storage_id".to_string(),
+      serde_json::to_value(("list_int", [0, 1, 2, 2])).unwrap(),
+    );
+    let mut exec = GraphExecutor::new(graph, &TestModule).unwrap();
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.set_input("w", Tensor::from(&x)).unwrap();
+
+    let dump = exec.run_debug().unwrap();
+    assert_eq!(dump.keys(), &["mul:0".to_string(), "add_one:0".to_string()]);
+    assert_eq!(
+      dump.get("mul", 0).unwrap().to_vec::<f32>(),
+      vec![1f32, 4., 9., 16.]
+    );
+    assert_eq!(
+      dump.get("add_one", 0).unwrap().to_vec::<f32>(),
+      vec![2f32, 5., 10., 17.]
+    );
+    assert!(dump.get("add_one", 1).is_none());
+
+    let param_bytes = dump.to_param_dict().unwrap();
+    let params = load_param_dict(&param_bytes).unwrap();
+    assert_eq!(params.len(), 2);
+    assert_eq!(
+      params["mul:0"].to_owned().to_vec::<f32>(),
+      vec![1f32, 4., 9., 16.]
+    );
+  }
+}
@@ -0,0 +1,318 @@
+use std::{
+  collections::HashMap,
+  ffi::{CStr, CString},
+  mem,
+  os::{
+    raw::{c_char, c_int, c_void},
+    unix::ffi::OsStrExt,
+  },
+  path::Path,
+  ptr,
+  sync::{Arc, Mutex, PoisonError},
+};
+
+use libc;
+
+use super::{
+  module::{find_function, ModuleObject},
+  packed_func::wrap_backend_packed_func,
+  registry::get_global_arc,
+  set_last_error, Module, PackedFunc, TVMAPISetLastError, TVMBackendAllocWorkspace,
+  TVMBackendFreeWorkspace, TVMBackendParallelBarrier, TVMBackendParallelLaunch, TVMFuncCall,
+};
+use errors::{ErrorKind, Result};
+use ffi::runtime::{BackendPackedCFunc, TVMFunctionHandle};
+
+/// The symbol whose value is the name of a library's entry function.
+/// @see `runtime::symbol::tvm_module_main` in `module.h`
+const TVM_MODULE_MAIN: &str = "__tvm_main__";
+/// The symbol which a library passes to `TVMBackendGetFuncFromEnv`.
+const TVM_MODULE_CTX: &str = "__tvm_module_ctx";
+
+/// A `Module` which loads the functions of a shared library exported by TVM
+/// using `tvm.build(...).export_library`.
+///
+/// # Examples
+///
+/// ```
+/// let lib = DsoModule::new("deploy_lib.so").unwrap();
+/// let mut exec = GraphExecutor::new(graph, &lib).unwrap();
+/// ```
+pub struct DsoModule {
+  /// Boxed so that the library can refer to it via `__tvm_module_ctx` while the
+  /// `DsoModule` moves.
+  env: Box<ModuleEnv>,
+}
+
+/// The functions which the library can look up using `TVMBackendGetFuncFromEnv`.
+struct ModuleEnv {
+  handle: *mut c_void,
+  imports: Mutex<Vec<Box<ModuleObject + Send + Sync>>>,
+  /// The functions which have been returned by `TVMBackendGetFuncFromEnv`. They are boxed so
+  /// that their handles remain valid as the map grows.
+  funcs: Mutex<HashMap<String, Box<Arc<PackedFunc>>>>,
+}
+
+unsafe impl Send for DsoModule {}
+unsafe impl Sync for DsoModule {}
+
+impl DsoModule {
+  /// Loads the library at `path` and sets the function pointers through which the library
+  /// calls into this runtime.
+  ///
+  /// Each library can be loaded at most once at a time.
+  pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+    let path = path.as_ref();
+    let cpath = CString::new(path.as_os_str().as_bytes())
+      .map_err(|_| ErrorKind::LoadModuleError(format!("invalid path {:?}", path)))?;
+    let handle = unsafe { libc::dlopen(cpath.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    ensure!(!handle.is_null(), ErrorKind::LoadModuleError(dl_error()));
+    let env = box ModuleEnv {
+      handle: handle,
+      imports: Mutex::new(Vec::new()),
+      funcs: Mutex::new(HashMap::new()),
+    };
+    if let Some(ctx) = env.symbol(TVM_MODULE_CTX) {
+      let ctx = ctx as *mut *const ModuleEnv;
+      if unsafe { !(*ctx).is_null() } {
+        unsafe { libc::dlclose(handle) };
+        bail!(ErrorKind::LoadModuleError(format!(
+          "{:?} is already loaded",
+          path
+        )));
+      }
+      unsafe { *ctx = &*env };
+    }
+    let module = DsoModule { env: env };
+    module.set_func_ptr("__TVMFuncCall", TVMFuncCall as *const c_void);
+    module.set_func_ptr("__TVMAPISetLastError", TVMAPISetLastError as *const c_void);
+    module.set_func_ptr(
+      "__TVMBackendGetFuncFromEnv",
+      TVMBackendGetFuncFromEnv as *const c_void,
+    );
+    module.set_func_ptr(
+      "__TVMBackendAllocWorkspace",
+      TVMBackendAllocWorkspace as *const c_void,
+    );
+    module.set_func_ptr(
+      "__TVMBackendFreeWorkspace",
+      TVMBackendFreeWorkspace as *const c_void,
+    );
+    module.set_func_ptr(
+      "__TVMBackendParallelLaunch",
+      TVMBackendParallelLaunch as *const c_void,
+    );
+    module.set_func_ptr(
+      "__TVMBackendParallelBarrier",
+      TVMBackendParallelBarrier as *const c_void,
+    );
+    Ok(module)
+  }
+
+  /// Appends `module` to the modules imported by the library as does TVM's `TVMModImport`.
+  /// Functions which are not in the library are resolved from its imports, in order of import,
+  /// both by `get_function` and when called from within the library.
+  pub fn import<M: 'static + Module + Send + Sync>(&mut self, module: M) -> &mut Self {
+    self.env.imports.lock().unwrap().push(box module);
+    self
+  }
+
+  /// Returns the name of the library's entry function, if it has one.
+  pub fn entry_name(&self) -> Option<String> {
+    self.symbol(TVM_MODULE_MAIN).map(|name| unsafe {
+      CStr::from_ptr(name as *const c_char)
+        .to_string_lossy()
+        .into_owned()
+    })
+  }
+
+  fn symbol(&self, name: &str) -> Option<*mut c_void> {
+    self.env.symbol(name)
+  }
+
+  /// Sets the function pointer slot `name`, if the library uses it, to `func`.
+  fn set_func_ptr(&self, name: &str, func: *const c_void) {
+    if let Some(slot) = self.symbol(name) {
+      unsafe { *(slot as *mut *const c_void) = func };
+    }
+  }
+}
+
+impl ModuleEnv {
+  fn symbol(&self, name: &str) -> Option<*mut c_void> {
+    let cname = CString::new(name).ok()?;
+    let sym = unsafe { libc::dlsym(self.handle, cname.as_ptr()) };
+    if sym.is_null() {
+      None
+    } else {
+      Some(sym)
+    }
+  }
+
+  fn get_function(&self, name: &str) -> Option<PackedFunc> {
+    self
+      .symbol(name)
+      .map(|func| {
+        wrap_backend_packed_func(unsafe { mem::transmute::<_, BackendPackedCFunc>(func) })
+      }).or_else(|| {
+        // the imports are only ever appended to, so they remain valid if a thread panicked
+        // while holding the lock, and this is called from `TVMBackendGetFuncFromEnv`, which
+        // must not panic
+        let imports = self.imports.lock().unwrap_or_else(PoisonError::into_inner);
+        find_function(&imports, name)
+      })
+  }
+}
+
+impl Module for DsoModule {
+  /// Returns the function named `name` from the library or, failing that, its imports.
+  /// The entry function can also be obtained as `"__tvm_main__"`.
+  fn get_function<S: AsRef<str>>(&self, name: S) -> Option<PackedFunc> {
+    if name.as_ref() == TVM_MODULE_MAIN {
+      return self
+        .entry_name()
+        .and_then(|entry_name| self.env.get_function(&entry_name));
+    }
+    self.env.get_function(name.as_ref())
+  }
+}
+
+impl Drop for DsoModule {
+  fn drop(&mut self) {
+    if let Some(ctx) = self.symbol(TVM_MODULE_CTX) {
+      unsafe { *(ctx as *mut *const ModuleEnv) = ptr::null() };
+    }
+    unsafe { libc::dlclose(self.env.handle) };
+  }
+}
+
+fn dl_error() -> String {
+  let err = unsafe { libc::dlerror() };
+  if err.is_null() {
+    "unknown error".to_string()
+  } else {
+    unsafe { CStr::from_ptr(err).to_string_lossy().into_owned() }
+  }
+}
+
+lazy_static! {
+  /// The global functions which have been returned by `TVMBackendGetFuncFromEnv` to code
+  /// without a module. They are boxed so that their handles remain valid as the map grows, and
+  /// are never freed because that code keeps its handles for the life of the process.
+  static ref ENV_GLOBAL_FUNCTIONS: Mutex<HashMap<String, Box<Arc<PackedFunc>>>> =
+    Mutex::new(HashMap::new());
+}
+
+/// Looks up `func_name` for a library loaded by `DsoModule`, whose `__tvm_module_ctx` is
+/// `mod_node`, in the library, then its imports, and then the global functions.
+/// A handle is valid until the `DsoModule` is dropped and keeps referring to the same function
+/// if a global function is later overridden or removed.
+///
+/// `mod_node` is null for `--system-lib` code, whose `__tvm_module_ctx` is never set, and once
+/// the `DsoModule` has been dropped. Only the global functions are then searched, and the
+/// handles are valid for the life of the process.
+///
+/// Returns -1 and sets the last error if the function is not found or an argument is null.
+#[no_mangle]
+pub extern "C" fn TVMBackendGetFuncFromEnv(
+  mod_node: *mut c_void,
+  func_name: *const c_char,
+  out: *mut TVMFunctionHandle,
+) -> c_int {
+  if func_name.is_null() || out.is_null() {
+    set_last_error("TVMBackendGetFuncFromEnv called with a null argument");
+    return -1;
+  }
+  let name = unsafe { CStr::from_ptr(func_name).to_string_lossy().into_owned() };
+  let env = unsafe { (mod_node as *const ModuleEnv).as_ref() };
+  let funcs = env.map_or(&*ENV_GLOBAL_FUNCTIONS, |env| &env.funcs);
+  let mut funcs = match funcs.lock() {
+    Ok(funcs) => funcs,
+    Err(_) => {
+      set_last_error(format!(
+        "cannot look up function `{}` because another lookup panicked",
+        name
+      ));
+      return -1;
+    }
+  };
+  if !funcs.contains_key(&name) {
+    let func = env
+      .and_then(|env| env.get_function(&name).map(Arc::new))
+      .or_else(|| get_global_arc(&name));
+    match func {
+      Some(func) => funcs.insert(name.clone(), box func),
+      None => {
+        set_last_error(format!("function `{}` not found", name));
+        return -1;
+      }
+    };
+  }
+  unsafe { *out = &*funcs[&name] as *const Arc<PackedFunc> as TVMFunctionHandle };
+  0
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use runtime::{last_error, register_global, TVMArgValue, TVMRetValue};
+
+  #[test]
+  fn test_load_missing() {
+    let err = DsoModule::new("/nonexistent/deploy_lib.so").err().unwrap();
+    assert!(err.to_string().starts_with("could not load module"));
+  }
+
+  #[test]
+  fn test_get_global_from_null_env() {
+    let func: PackedFunc = box |_args: &[TVMArgValue]| Ok(TVMRetValue::from(3i64));
+    register_global("test.from_env", func, false).unwrap();
+    let mut handle = ptr::null_mut();
+    let status = TVMBackendGetFuncFromEnv(
+      ptr::null_mut(),
+      b"test.from_env\0".as_ptr() as *const c_char,
+      &mut handle,
+    );
+    assert_eq!(status, 0);
+    let (mut ret_val, mut ret_type_code) = (unsafe { mem::zeroed() }, 0);
+    let status = TVMFuncCall(
+      handle,
+      ptr::null_mut(),
+      ptr::null_mut(),
+      0,
+      &mut ret_val,
+      &mut ret_type_code,
+    );
+    assert_eq!(status, 0);
+    assert_eq!(unsafe { ret_val.v_int64 }, 3);
+
+    // the handle is kept, referring to the same function, if the function is overridden
+    let func: PackedFunc = box |_args: &[TVMArgValue]| Ok(TVMRetValue::from(4i64));
+    register_global("test.from_env", func, true).unwrap();
+    let mut new_handle = ptr::null_mut();
+    let status = TVMBackendGetFuncFromEnv(
+      ptr::null_mut(),
+      b"test.from_env\0".as_ptr() as *const c_char,
+      &mut new_handle,
+    );
+    assert_eq!(status, 0);
+    assert_eq!(new_handle, handle);
+  }
+
+  #[test]
+  fn test_get_func_from_unloaded_env() {
+    let mut handle = ptr::null_mut();
+    let status = TVMBackendGetFuncFromEnv(
+      ptr::null_mut(),
+      b"add_one\0".as_ptr() as *const c_char,
+      &mut handle,
+    );
+    assert_eq!(status, -1);
+    assert!(handle.is_null());
+    assert!(last_error().contains("function `add_one`"));
+
+    let status = TVMBackendGetFuncFromEnv(ptr::null_mut(), ptr::null(), &mut handle);
+    assert_eq!(status, -1);
+    assert!(last_error().contains("null argument"));
+  }
+}
@@ -1,19 +1,34 @@
-use std::{cmp, collections::HashMap, convert::TryFrom, iter::FromIterator, mem, str};
+#[cfg(not(any(target_arch = "wasm32", target_env = "sgx")))]
+use std::time::Instant;
+use std::{
+  cmp,
+  collections::{HashMap, HashSet},
+  convert::TryFrom,
+  fmt,
+  io::Read,
+  iter::FromIterator,
+  marker::PhantomData,
+  mem, ptr, slice, str,
+};
 
-use nom::{alpha1, digit1, le_i32, le_i64, le_u16, le_u32, le_u64, le_u8, types::CompleteStr};
+use nom::{le_i32, le_i64, le_u16, le_u32, le_u64, le_u8};
 use serde;
 use serde_json;
 
-use super::{DataType, Module, Storage, TVMArgValue, TVMContext, Tensor};
-use errors::{Error, ErrorKind, Result};
-use ffi::runtime::{
-  DLDataTypeCode_kDLFloat, DLDataTypeCode_kDLInt, DLDataTypeCode_kDLUInt, DLTensor,
+#[cfg(not(any(target_arch = "wasm32", target_env = "sgx")))]
+use super::Profiler;
+use super::{
+  op_scheduler::OpScheduler, packed_func::call_error_message, storage_plan::storage_num_bytes,
+  DataType, DebugDump, Module, PackedFunc, ParamReader, SharedParams, Storage, StoragePlan,
+  TVMArgValue, TVMContext, Tensor,
 };
+use errors::{Error, ErrorKind, Result};
+use ffi::runtime::{DLDeviceType_kDLCPU, DLTensor};
 
 // Magic number for NDArray file. @see `kTVMNDArrayMagic` in `ndarray.h`
-const _NDARRAY_MAGIC: u64 = 0xDD5E40F096B4A13F;
+pub(super) const NDARRAY_MAGIC: u64 = 0xDD5E40F096B4A13F;
 // Magic number for NDArray list file. @see `kTVMNDArrayListMagic` in `graph_runtime.h`
-const _NDARRAY_LIST_MAGIC: u64 = 0xF7E58D4F05049CB7;
+pub(super) const NDARRAY_LIST_MAGIC: u64 = 0xF7E58D4F05049CB7;
 
 /// A TVM computation graph.
 ///
@@ -28,24 +43,355 @@ pub struct Graph {
   pub nodes: Vec<Node>,
   pub arg_nodes: Vec<usize>,
   pub heads: Vec<Entry>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub node_row_ptr: Option<Vec<usize>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub attrs: Option<HashMap<String, serde_json::Value>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Entry {
   pub id: usize,
   pub index: usize,
   pub version: usize,
 }
 
+/// Entries are serialized as `[id, index, version]`, as expected by TVM.
+impl serde::Serialize for Entry {
+  fn serialize<S: serde::Serializer>(
+    &self,
+    serializer: S,
+  ) -> ::std::result::Result<S::Ok, S::Error> {
+    (self.id, self.index, self.version).serialize(serializer)
+  }
+}
+
+/// Entries are deserialized from `[id, index, version]` or, as written by older NNVM versions,
+/// `[id, index]`.
+impl<'de> serde::Deserialize<'de> for Entry {
+  fn deserialize<D: serde::Deserializer<'de>>(
+    deserializer: D,
+  ) -> ::std::result::Result<Self, D::Error> {
+    let fields: Vec<usize> = serde::Deserialize::deserialize(deserializer)?;
+    match fields.len() {
+      2 | 3 => Ok(Entry {
+        id: fields[0],
+        index: fields[1],
+        version: fields.get(2).cloned().unwrap_or(0),
+      }),
+      len => Err(serde::de::Error::invalid_length(
+        len,
+        &"an entry of [id, index] or [id, index, version]",
+      )),
+    }
+  }
+}
+
 impl Graph {
-  fn entry_index(&self, entry: &Entry) -> Result<usize> {
+  pub(super) fn entry_index(&self, entry: &Entry) -> Result<usize> {
     self
       .node_row_ptr
       .as_ref()
-      .map(|nrp| nrp[entry.id] + entry.index)
-      .ok_or("Missing node_row_ptr.".into())
+      .ok_or(ErrorKind::GraphFormatError(
+        "Missing node_row_ptr.".to_string(),
+      ))?.get(entry.id)
+      .map(|row| row + entry.index)
+      .ok_or(
+        ErrorKind::GraphFormatError(format!("Entry refers to nonexistent node {}", entry.id))
+          .into(),
+      )
+  }
+
+  /// Checks that this graph is well-formed enough to be executed.
+  /// Errors are reported as a `GraphFormatError` which names the offending node.
+  pub fn validate(&self) -> Result<()> {
+    let node_err = |i: usize, msg: &str| -> Error {
+      ErrorKind::GraphFormatError(format!("node {} (`{}`) {}", i, self.nodes[i].name, msg)).into()
+    };
+
+    let nrp = self
+      .node_row_ptr
+      .as_ref()
+      .ok_or(ErrorKind::GraphFormatError(
+        "Missing node_row_ptr.".to_string(),
+      ))?;
+    ensure!(
+      nrp.len() == self.nodes.len() + 1,
+      ErrorKind::GraphFormatError(format!(
+        "node_row_ptr has {} entries but graph has {} nodes",
+        nrp.len(),
+        self.nodes.len()
+      ))
+    );
+    ensure!(
+      nrp[0] == 0,
+      ErrorKind::GraphFormatError(format!("node_row_ptr must start at 0, not {}", nrp[0]))
+    );
+
+    for (i, node) in self.nodes.iter().enumerate() {
+      ensure!(
+        nrp[i] <= nrp[i + 1],
+        node_err(i, "has a decreasing node_row_ptr")
+      );
+      let num_outputs = nrp[i + 1] - nrp[i];
+      match node.op.as_str() {
+        "null" => ensure!(
+          num_outputs == 1,
+          node_err(i, &format!("is a `null` op with {} outputs", num_outputs))
+        ),
+        "tvm_op" => {
+          let attrs = node
+            .parse_attrs()
+            .map_err(|err| node_err(i, &format!("has invalid attrs: {}", err)))?;
+          ensure!(
+            attrs.num_outputs == num_outputs,
+            node_err(
+              i,
+              &format!(
+                "has {} outputs but node_row_ptr allots {}",
+                attrs.num_outputs, num_outputs
+              )
+            )
+          );
+        }
+        op => bail!(node_err(i, &format!("has unsupported op `{}`", op))),
+      }
+      for (j, entry) in node.inputs.iter().enumerate() {
+        ensure!(
+          entry.id < i,
+          node_err(
+            i,
+            &format!(
+              "input {} refers to node {} which does not precede it",
+              j, entry.id
+            )
+          )
+        );
+        ensure!(
+          entry.index < nrp[entry.id + 1] - nrp[entry.id],
+          node_err(
+            i,
+            &format!(
+              "input {} refers to nonexistent output {} of node {}",
+              j, entry.index, entry.id
+            )
+          )
+        );
+      }
+    }
+
+    for (i, head) in self.heads.iter().enumerate() {
+      ensure!(
+        head.id < self.nodes.len() && head.index < nrp[head.id + 1] - nrp[head.id],
+        ErrorKind::GraphFormatError(format!(
+          "head {} refers to nonexistent output {} of node {}",
+          i, head.index, head.id
+        ))
+      );
+    }
+
+    for &id in self.arg_nodes.iter() {
+      ensure!(
+        id < self.nodes.len(),
+        ErrorKind::GraphFormatError(format!("arg_nodes refers to nonexistent node {}", id))
+      );
+      ensure!(
+        self.nodes[id].op == "null",
+        node_err(id, "is listed in arg_nodes but is not a `null` op")
+      );
+    }
+    for (i, node) in self.nodes.iter().enumerate() {
+      ensure!(
+        node.op != "null" || self.arg_nodes.contains(&i),
+        node_err(i, "is a `null` op but is not listed in arg_nodes")
+      );
+    }
+
+    let num_entries = nrp[self.nodes.len()];
+    let entry_attrs = [
+      "storage_id",
+      "shape",
+      "dltype",
+      "device_index",
+      "storage_scope",
+    ];
+    for attr in entry_attrs.iter() {
+      if !self
+        .attrs
+        .as_ref()
+        .map_or(false, |attrs| attrs.contains_key(*attr))
+      {
+        continue;
+      }
+      let values = self.get_list_attr::<serde_json::Value>(attr)?;
+      ensure!(
+        values.len() == num_entries,
+        ErrorKind::GraphFormatError(format!(
+          "`{}` attr has {} entries but graph has {} node outputs",
+          attr,
+          values.len(),
+          num_entries
+        ))
+      );
+    }
+
+    // this runtime executes every op on the CPU using global memory
+    if let Ok(device_indices) = self.get_list_attr::<i64>("device_index") {
+      ensure!(
+        device_indices.windows(2).all(|pair| pair[0] == pair[1]),
+        ErrorKind::GraphFormatError("Heterogeneous graphs are not supported".to_string())
+      );
+    }
+    if let Ok(storage_scopes) = self.get_list_attr::<String>("storage_scope") {
+      if let Some(scope) = storage_scopes
+        .iter()
+        .find(|scope| !scope.is_empty() && scope.as_str() != "global")
+      {
+        bail!(ErrorKind::GraphFormatError(format!(
+          "Unsupported storage scope `{}`",
+          scope
+        )))
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Returns the entry index of the arg node with name `name`, if it exists.
+  fn arg_entry_index(&self, name: &str) -> Option<usize> {
+    (0..self.nodes.len())
+      .skip_while(|&i| self.nodes[i].name != name)
+      .nth(0)
+      .and_then(|i| {
+        if self.arg_nodes.iter().any(|&id| id == i) {
+          self.node_row_ptr.as_ref().map(|nrp| nrp[i])
+        } else {
+          None
+        }
+      })
+  }
+
+  /// Returns the shape of each node output.
+  pub(super) fn shapes(&self) -> Result<Vec<Vec<i64>>> {
+    self.get_list_attr::<Vec<i64>>("shape")
+  }
+
+  /// Returns the `DataType` of each node output.
+  pub(super) fn dtypes(&self) -> Result<Vec<DataType>> {
+    self
+      .get_list_attr::<String>("dltype")?
+      .iter()
+      .map(|dltype| dltype.parse())
+      .collect()
+  }
+
+  /// Serializes this graph to the JSON format read by `Graph::try_from` and TVM.
+  pub fn to_json(&self) -> Result<String> {
+    Ok(serde_json::to_string(self)?)
+  }
+
+  /// Renders this graph in the Graphviz DOT language.
+  ///
+  /// Each node is labeled with its name, its `func_name` (or `null`) and, when the graph has the
+  /// corresponding attrs, the shape, dltype and `storage_id` of each of its outputs.
+  /// Nodes whose outputs share storage with other entries have the same fill color and graph
+  /// outputs are drawn with a double border.
+  pub fn to_dot(&self) -> String {
+    let node_row_ptr = self.node_row_ptr.as_ref();
+    let shapes = self.shapes().ok();
+    let dltypes = self.get_list_attr::<String>("dltype").ok();
+    let storage_ids = self.get_list_attr::<usize>("storage_id").ok();
+
+    let has_entry_attrs = shapes.is_some() || dltypes.is_some() || storage_ids.is_some();
+
+    // assign a color to each storage which holds more than one entry
+    let mut storage_colors = HashMap::new();
+    if let Some(ref storage_ids) = storage_ids {
+      for &storage_id in storage_ids.iter() {
+        if storage_ids.iter().filter(|&&sid| sid == storage_id).count() > 1 {
+          let num_colors = storage_colors.len();
+          storage_colors
+            .entry(storage_id)
+            .or_insert(num_colors % 12 + 1);
+        }
+      }
+    }
+
+    let mut dot = "digraph {\n  node [fontname=\"monospace\"];\n".to_string();
+    for (i, node) in self.nodes.iter().enumerate() {
+      let mut label = vec![
+        node.name.clone(),
+        match node.op.as_str() {
+          "null" => "null".to_string(),
+          _ => node
+            .attrs
+            .as_ref()
+            .and_then(|attrs| attrs.get("func_name"))
+            .cloned()
+            .unwrap_or_else(|| node.op.clone()),
+        },
+      ];
+      let mut fill_color = None;
+      if let Some(nrp) = node_row_ptr.filter(|nrp| has_entry_attrs && i + 1 < nrp.len()) {
+        for (index, eid) in (nrp[i]..nrp[i + 1]).enumerate() {
+          let mut output = format!("{}:", index);
+          if let Some(shape) = shapes.as_ref().and_then(|shapes| shapes.get(eid)) {
+            output.push_str(&format!(" {:?}", shape));
+          }
+          if let Some(dltype) = dltypes.as_ref().and_then(|dltypes| dltypes.get(eid)) {
+            output.push_str(&format!(" {}", dltype));
+          }
+          if let Some(&storage_id) = storage_ids.as_ref().and_then(|sids| sids.get(eid)) {
+            output.push_str(&format!(" @{}", storage_id));
+            fill_color = fill_color.or(storage_colors.get(&storage_id));
+          }
+          label.push(output);
+        }
+      }
+
+      let label = label
+        .iter()
+        .map(|line| escape_dot(line))
+        .collect::<Vec<String>>();
+      let mut node_attrs = vec![
+        format!("label=\"{}\"", label.join("\\n")),
+        format!(
+          "shape={}",
+          if node.op == "null" { "ellipse" } else { "box" }
+        ),
+      ];
+      if let Some(color) = fill_color {
+        node_attrs.push(format!("style=filled, fillcolor=\"/set312/{}\"", color));
+      }
+      if self.heads.iter().any(|head| head.id == i) {
+        node_attrs.push("peripheries=2".to_string());
+      }
+      dot.push_str(&format!("  n{} [{}];\n", i, node_attrs.join(", ")));
+      for entry in node.inputs.iter() {
+        dot.push_str(&format!(
+          "  n{} -> n{} [label=\"{}\"];\n",
+          entry.id, i, entry.index
+        ));
+      }
+    }
+    dot.push_str("}\n");
+    dot
+  }
+
+  /// Deserializes a graph attribute which has a value for each node output. Such attributes
+  /// are usually encoded as a `["<type>", [values...]]` pair, but may be a bare list.
+  pub(super) fn get_list_attr<T: serde::de::DeserializeOwned>(&self, attr: &str) -> Result<Vec<T>> {
+    let value = match self.get_attr::<serde_json::Value>(attr)? {
+      serde_json::Value::Array(mut pair) => {
+        if pair.len() == 2 && pair[0].is_string() && pair[1].is_array() {
+          pair.pop().unwrap()
+        } else {
+          serde_json::Value::Array(pair)
+        }
+      }
+      value => value,
+    };
+    Ok(serde_json::from_value(value)?)
   }
 
   /// Attempt to deserialize a JSON attribute to a type `T`.
@@ -70,7 +416,16 @@ pub struct Node {
   pub op: String,
   pub name: String,
   pub inputs: Vec<Entry>,
+  /// Older NNVM versions name this field `attr` and newer TVM versions may encode non-string
+  /// values, which are stored in their JSON representation.
+  #[serde(
+    default,
+    alias = "attr",
+    deserialize_with = "deserialize_node_attrs",
+    skip_serializing_if = "Option::is_none"
+  )]
   pub attrs: Option<HashMap<String, String>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub control_deps: Option<Vec<Entry>>,
 }
 
@@ -80,6 +435,21 @@ struct NodeAttrs {
   flatten_data: bool,
 }
 
+fn deserialize_node_attrs<'de, D: serde::Deserializer<'de>>(
+  deserializer: D,
+) -> ::std::result::Result<Option<HashMap<String, String>>, D::Error> {
+  let attrs: Option<HashMap<String, serde_json::Value>> =
+    serde::Deserialize::deserialize(deserializer)?;
+  Ok(attrs.map(|attrs| {
+    attrs
+      .into_iter()
+      .map(|(key, value)| match value {
+        serde_json::Value::String(value) => (key, value),
+        value => (key, value.to_string()),
+      }).collect()
+  }))
+}
+
 impl Node {
   fn parse_attrs(&self) -> Result<NodeAttrs> {
     let attrs = self
@@ -90,17 +460,20 @@ impl Node {
       .get("func_name")
       .ok_or(format!("Node `{}` is missing attrs.func_name", self.name))?
       .to_string();
-    let num_outputs = attrs
-      .get("num_outputs")
-      .ok_or(format!("Node `{}` is missing attrs.num_outputs", self.name))?
-      .parse::<usize>()?;
-    let flatten_data = attrs
-      .get("flatten_data")
-      .ok_or(format!(
-        "Node `{}` is missing attrs.flatten_data",
-        self.name
-      ))?.parse::<u8>()?
-      == 1;
+    let num_outputs = match attrs.get("num_outputs") {
+      Some(num_outputs) => num_outputs.parse::<usize>()?,
+      None => 1,
+    };
+    // Relay-based TVM may omit `flatten_data`, which then defaults to false
+    let flatten_data = match attrs.get("flatten_data").map(|f| f.as_str()) {
+      None | Some("0") | Some("false") => false,
+      Some("1") | Some("true") => true,
+      Some(flatten_data) => bail!(
+        "Node `{}` has invalid attrs.flatten_data `{}`",
+        self.name,
+        flatten_data
+      ),
+    };
     Ok(NodeAttrs {
       func_name,
       num_outputs,
@@ -112,15 +485,28 @@ impl Node {
 impl<'a> TryFrom<&'a String> for Graph {
   type Error = Error;
   fn try_from(graph_json: &String) -> Result<Self> {
-    let graph = serde_json::from_str(graph_json)?;
-    Ok(graph)
+    Graph::try_from(graph_json.as_str())
   }
 }
 
+/// Graphs may be in the layout of NNVM or of the Relay-based graph runtime.
+/// A missing `node_row_ptr` is computed from the number of outputs of each node.
 impl<'a> TryFrom<&'a str> for Graph {
   type Error = Error;
   fn try_from(graph_json: &'a str) -> Result<Self> {
-    let graph = serde_json::from_str(graph_json)?;
+    let mut graph: Graph = serde_json::from_str(graph_json)?;
+    if graph.node_row_ptr.is_none() {
+      let mut node_row_ptr = vec![0];
+      for node in graph.nodes.iter() {
+        let num_outputs = match node.op.as_str() {
+          "null" => 1,
+          _ => node.parse_attrs()?.num_outputs,
+        };
+        let row = node_row_ptr[node_row_ptr.len() - 1] + num_outputs;
+        node_row_ptr.push(row);
+      }
+      graph.node_row_ptr = Some(node_row_ptr);
+    }
     Ok(graph)
   }
 }
@@ -141,70 +527,679 @@ impl<'a> TryFrom<&'a str> for Graph {
 /// let graph = Graph::try_from(&fs::read_to_string("graph.json").unwrap()).unwrap();
 ///
 /// let mut exec = GraphExecutor::new(graph, &syslib).unwrap();
-/// exec.load_params(params);
+/// exec.load_params(params, ParamCheck::Strict).unwrap();
 ///
 /// let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
-/// exec.set_input("data", x.into());
-/// exec.run();
+/// exec.set_input("data", x.into()).unwrap();
+/// exec.run().unwrap();
 /// let output = exec.get_output(0).unwrap();
 ///
 /// println!("{:#?}", Array::try_from(output).unwrap());
 /// ```
 pub struct GraphExecutor<'m, 't> {
   graph: Graph,
-  op_execs: Vec<Box<Fn() + 'm>>,
+  op_execs: Vec<OpExec>,
   tensors: Vec<Tensor<'t>>,
+  /// The names of inputs which were set from a param dict.
+  param_names: HashSet<String>,
+  /// Keeps alive the storage of the params bound by `new_with_shared_params`.
+  shared_params: Option<SharedParams>,
+  /// The names of params which are still bound to storage passed to `new_with_params`, such as
+  /// that of `shared_params` or of a `MappedParams`, which the executor must not write.
+  shared_param_names: HashSet<String>,
+  /// Runs the ops concurrently if set by `set_max_concurrent_ops`.
+  scheduler: Option<OpScheduler>,
+  /// The op functions must not outlive the `Module` from which they came.
+  _lib: PhantomData<&'m ()>,
+}
+
+/// Describes a tensor which is an input or output of a `GraphExecutor`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TensorInfo {
+  /// The input name or, for outputs, `"<node name>:<output index>"`.
+  pub name: String,
+  pub shape: Vec<i64>,
+  pub dtype: DataType,
+  /// The number of bytes occupied by the tensor's data.
+  pub num_bytes: usize,
+}
+
+/// Determines how `GraphExecutor::load_params` treats params which do not match the graph.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParamCheck {
+  /// Load nothing if any param is unknown or mismatched.
+  Strict,
+  /// Load the params which can be copied into their inputs and skip the rest.
+  Lenient,
+}
+
+/// A comparison of a param dict against the inputs of a graph.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParamReport {
+  /// The names of the params which were loaded.
+  pub loaded: Vec<String>,
+  /// The names of the params which are not graph inputs.
+  pub unknown: Vec<String>,
+  /// The names of the graph inputs for which there is no param. These are usually the inputs
+  /// which are set for each run.
+  pub missing: Vec<String>,
+  pub mismatched: Vec<ParamMismatch>,
+}
+
+/// A param whose shape or dtype differs from that of the graph input of the same name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamMismatch {
+  /// The input as described by the graph's `shape` and `dltype` attrs.
+  pub expected: TensorInfo,
+  pub actual: TensorInfo,
+}
+
+impl ParamReport {
+  /// Returns `true` if every param matches a graph input.
+  pub fn is_ok(&self) -> bool {
+    self.unknown.is_empty() && self.mismatched.is_empty()
+  }
+}
+
+impl fmt::Display for ParamReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let mut problems = Vec::new();
+    if !self.unknown.is_empty() {
+      problems.push(format!("unknown params: {}", self.unknown.join(", ")));
+    }
+    if !self.missing.is_empty() {
+      problems.push(format!("missing params: {}", self.missing.join(", ")));
+    }
+    for mismatch in self.mismatched.iter() {
+      problems.push(format!(
+        "param `{}` should be {} {:?} ({} bytes) but is {} {:?} ({} bytes)",
+        mismatch.expected.name,
+        mismatch.expected.dtype,
+        mismatch.expected.shape,
+        mismatch.expected.num_bytes,
+        mismatch.actual.dtype,
+        mismatch.actual.shape,
+        mismatch.actual.num_bytes
+      ));
+    }
+    write!(f, "{}", problems.join("; "))
+  }
+}
+
+/// Performs the computation of the node with index `node_id`.
+struct OpExec {
+  node_id: usize,
+  node_name: String,
+  func_name: String,
+  func: PackedFunc,
+  /// The entry indices of the op's inputs followed by those of its outputs.
+  arg_indices: Vec<usize>,
+  flatten_data: bool,
+  /// The arguments to `func`, which point to the `Tensor`s at `arg_indices`.
+  dl_tensors: Vec<DLTensor>,
+}
+
+impl OpExec {
+  /// Points the arguments of this op at the corresponding `tensors`.
+  fn bind(&mut self, tensors: &[Tensor]) {
+    let flatten_data = self.flatten_data;
+    self.dl_tensors = self
+      .arg_indices
+      .iter()
+      .map(|&idx| DLTensor::from_tensor(&tensors[idx], flatten_data))
+      .collect();
+  }
+
+  /// Calls the op's function. Errors identify the node and function which failed.
+  fn run(&self) -> Result<()> {
+    let args = self
+      .dl_tensors
+      .iter()
+      .map(|t| t.into())
+      .collect::<Vec<TVMArgValue>>();
+    match (self.func)(args.as_slice()) {
+      Ok(_) => Ok(()),
+      Err(err) => bail!(ErrorKind::OpExecError(
+        self.node_name.clone(),
+        self.func_name.clone(),
+        call_error_message(err)
+      )),
+    }
+  }
 }
 
+// `OpScheduler` shares the `OpExec`s between its worker threads, which may run different ops
+// at once but never the same op twice at once. This is safe because:
+// - running an op only reads its `dl_tensors`. Their shape, strides and data pointers point into
+//   the executor's `tensors`, which are only rebound, reallocated or freed through
+//   `&mut GraphExecutor`, so they stay valid and unchanged while `run` borrows the executor.
+// - ops which run concurrently do not share storage (@see `op_dependencies`), so the data which
+//   one op writes through its pointers is not accessed by another.
+// - the `TVMArgValue`s passed to `func` are created on the thread which runs the op.
+// - op functions, such as those compiled by TVM, can be called from several threads at once,
+//   as is needed when two ops call the same function.
+unsafe impl Sync for OpExec {}
+
 unsafe impl<'m, 't> Send for GraphExecutor<'m, 't> {}
 
 impl<'m, 't> GraphExecutor<'m, 't> {
   pub fn new<M: 'm + Module>(graph: Graph, lib: &'m M) -> Result<Self> {
-    let tensors = Self::setup_storages(&graph)?;
-    Ok(GraphExecutor {
+    Self::new_with_params(graph, lib, HashMap::new())
+  }
+
+  /// Creates a `GraphExecutor` which uses the storage of `params` directly instead of
+  /// allocating storage for them and copying them in. The executor never writes to the
+  /// storage of `params`: setting a bound param first gives the executor its own copy.
+  ///
+  /// A param is bound without copying if it is contiguous, aligned as its dtype requires, and
+  /// the only occupant of its `storage_id`. Other params are copied as by `load_params`.
+  ///
+  /// Fails if any param is unknown or mismatched, as does `load_params` with
+  /// `ParamCheck::Strict`.
+  ///
+  /// If the graph has no `storage_id` attr, storage is assigned using `StoragePlan::new`.
+  pub fn new_with_params<M: 'm + Module>(
+    mut graph: Graph,
+    lib: &'m M,
+    mut params: HashMap<String, Tensor<'t>>,
+  ) -> Result<Self> {
+    graph.validate()?;
+    if graph
+      .attrs
+      .as_ref()
+      .map_or(false, |attrs| !attrs.contains_key("storage_id"))
+    {
+      let plan = StoragePlan::new(&graph)?;
+      graph.attrs.as_mut().unwrap().insert(
+        "storage_id".to_string(),
+        serde_json::to_value(("list_int", plan.storage_ids))?,
+      );
+    }
+    let report = Self::param_report(&graph, &params)?;
+    ensure!(
+      report.is_ok(),
+      ErrorKind::LoadGraphParamsError(report.to_string())
+    );
+    let param_names = params
+      .keys()
+      .filter(|name| graph.arg_entry_index(name).is_some())
+      .cloned()
+      .collect::<HashSet<String>>();
+    let tensors = Self::setup_storages(&graph, &mut params)?;
+    let bound_names = param_names
+      .iter()
+      .filter(|name| !params.contains_key(*name))
+      .cloned()
+      .collect();
+    let mut exec = GraphExecutor {
       op_execs: Self::setup_op_execs(&graph, lib, &tensors)?,
       tensors: tensors,
       graph: graph,
-    })
+      param_names: param_names,
+      shared_params: None,
+      shared_param_names: bound_names,
+      scheduler: None,
+      _lib: PhantomData,
+    };
+    exec.load_params(params, ParamCheck::Strict)?;
+    Ok(exec)
+  }
+
+  /// Creates a `GraphExecutor` which holds `params` and binds to them as in `new_with_params`.
+  pub fn new_with_shared_params<M: 'm + Module>(
+    graph: Graph,
+    lib: &'m M,
+    params: &SharedParams,
+  ) -> Result<Self> {
+    let mut exec = Self::new_with_params(graph, lib, params.views())?;
+    exec.shared_params = Some(params.clone());
+    Ok(exec)
   }
 
   /// Runs the computation graph.
-  pub fn run(&self) {
-    self.op_execs.iter().for_each(|op_exec| {
-      op_exec();
-    });
+  ///
+  /// If an op fails, no further ops are started and the error names the op's node and
+  /// function. The outputs are then invalid until the graph is run successfully.
+  pub fn run(&self) -> Result<()> {
+    let op_execs = &self.op_execs;
+    match self.scheduler {
+      Some(ref scheduler) => scheduler.run(|i| op_execs[i].run()),
+      None => op_execs.iter().try_for_each(OpExec::run),
+    }
   }
 
-  /// Allocates `Storages` for each `storage_id` and returns `Tensor`s to hold each output.
-  fn setup_storages<'a>(graph: &'a Graph) -> Result<Vec<Tensor<'t>>> {
-    let storage_ids = graph.get_attr::<(String, Vec<usize>)>("storage_id")?.1;
-    let shapes = graph.get_attr::<(String, Vec<Vec<i64>>)>("shape")?.1;
-    let dtypes = graph
-      .get_attr::<(String, Vec<String>)>("dltype")?
-      .1
+  /// Makes `run` execute up to `max_concurrent_ops` independent ops at once on a pool of
+  /// worker threads. Ops are independent if neither reads nor overwrites the storage of an
+  /// output of the other. A value of `1` restores sequential execution.
+  ///
+  /// `max_concurrent_ops` is capped at the number of ops. Each worker thread starts its own
+  /// pool for the intra-op parallelism of TVM functions, whose size is given by
+  /// `TVM_NUM_THREADS` or else by the number of physical cores, so up to `max_concurrent_ops`
+  /// times that many threads may run at once. Limit `TVM_NUM_THREADS` to avoid oversubscribing
+  /// the cores.
+  pub fn set_max_concurrent_ops(&mut self, max_concurrent_ops: usize) -> Result<()> {
+    self.scheduler = None;
+    let max_concurrent_ops = cmp::min(max_concurrent_ops, self.op_execs.len());
+    if max_concurrent_ops > 1 {
+      self.scheduler = Some(OpScheduler::new(
+        self.op_dependencies()?,
+        max_concurrent_ops,
+      ));
+    }
+    Ok(())
+  }
+
+  /// Returns the indices of the `op_execs` which must complete before each op can start,
+  /// either because the op reads their outputs or because it overwrites storage they use.
+  fn op_dependencies(&self) -> Result<Vec<Vec<usize>>> {
+    let storage_ids = self.graph.get_list_attr::<usize>("storage_id")?;
+    let mut last_writers: HashMap<usize, usize> = HashMap::new();
+    let mut readers: HashMap<usize, Vec<usize>> = HashMap::new();
+    Ok(
+      self
+        .op_execs
+        .iter()
+        .enumerate()
+        .map(|(i, op_exec)| {
+          let num_inputs = self.graph.nodes[op_exec.node_id].inputs.len();
+          let mut deps = Vec::new();
+          for &eid in op_exec.arg_indices[..num_inputs].iter() {
+            let storage_id = storage_ids[eid];
+            deps.extend(last_writers.get(&storage_id));
+            readers.entry(storage_id).or_insert_with(Vec::new).push(i);
+          }
+          for &eid in op_exec.arg_indices[num_inputs..].iter() {
+            let storage_id = storage_ids[eid];
+            deps.extend(last_writers.get(&storage_id));
+            deps.extend(
+              readers
+                .remove(&storage_id)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|&reader| reader != i),
+            );
+            last_writers.insert(storage_id, i);
+          }
+          deps.sort();
+          deps.dedup();
+          deps
+        }).collect(),
+    )
+  }
+
+  /// Changes the shapes of the graph inputs named in `shapes` and re-derives those of the
+  /// entries computed from them: an op output whose leading dimension matched that of the
+  /// reshaped inputs takes their new leading dimension. The shapes of op outputs which don't
+  /// follow the leading dimension may be given as `node_name:index`, as named by `outputs`.
+  /// Every input whose leading dimension changes must change it from and to the same value.
+  ///
+  /// The ops are run once on zeroed scratch storage to check that they accept the new shapes,
+  /// as ops compiled by TVM check the shapes of their arguments. If an op fails, the executor
+  /// keeps its previous shapes and storage, and the op's error is returned.
+  ///
+  /// Storage is reallocated as necessary, so reshaped inputs must be set again before running.
+  pub fn reshape_inputs(&mut self, shapes: &HashMap<String, Vec<i64>>) -> Result<()> {
+    let old_shapes = self
+      .tensors
       .iter()
-      .map(|dltype| {
-        if let Ok((_, dtype)) = tvm_str_to_type(CompleteStr(dltype)) {
-          Ok(dtype)
-        } else {
-          Err(ErrorKind::GraphFormatError(format!("Invalid dltype: {}", dltype).to_string()).into())
+      .map(|tensor| tensor.shape.clone())
+      .collect::<Vec<Vec<i64>>>();
+    let mut new_shapes = old_shapes.clone();
+    let mut is_given = vec![false; self.tensors.len()];
+    let mut batch_dims = None;
+    for (name, shape) in shapes.iter() {
+      let eid = self.entry_index_by_name(name)?;
+      ensure!(
+        num_elements(shape).is_some(),
+        "Invalid shape for `{}`: {:?}",
+        name,
+        shape
+      );
+      if let (Some(_), Some(&old_batch), Some(&new_batch)) = (
+        self.get_input_index(name),
+        old_shapes[eid].first(),
+        shape.first(),
+      ) {
+        if old_batch != new_batch {
+          ensure!(
+            batch_dims.map_or(true, |dims| dims == (old_batch, new_batch)),
+            "All inputs must be reshaped from the same leading dimension to the same leading \
+             dimension"
+          );
+          batch_dims = Some((old_batch, new_batch));
+        }
+      }
+      new_shapes[eid] = shape.clone();
+      is_given[eid] = true;
+    }
+
+    if let Some((old_batch, new_batch)) = batch_dims {
+      let follows_batch = |old_shape: &Vec<i64>, new_shape: &Vec<i64>| {
+        old_shape.first() == Some(&old_batch) && new_shape.first() == Some(&new_batch)
+      };
+      let mut is_batched = izip!(old_shapes.iter(), new_shapes.iter(), is_given.iter())
+        .map(|(old_shape, new_shape, &given)| given && follows_batch(old_shape, new_shape))
+        .collect::<Vec<bool>>();
+      let node_row_ptr = self.graph.node_row_ptr.as_ref().unwrap();
+      for (i, node) in self.graph.nodes.iter().enumerate() {
+        let mut input_eids = node.inputs.iter().map(|entry| self.graph.entry_index(entry));
+        if !input_eids.try_fold(false, |any, eid| eid.map(|eid| any || is_batched[eid]))? {
+          continue;
+        }
+        for eid in node_row_ptr[i]..node_row_ptr[i + 1] {
+          if !is_given[eid] && old_shapes[eid].first() == Some(&old_batch) {
+            new_shapes[eid][0] = new_batch;
+          }
+          is_batched[eid] = follows_batch(&old_shapes[eid], &new_shapes[eid]);
+        }
+      }
+    }
+
+    let is_reshaped = old_shapes
+      .iter()
+      .zip(new_shapes.iter())
+      .map(|(old_shape, new_shape)| old_shape != new_shape)
+      .collect::<Vec<bool>>();
+    if !is_reshaped.contains(&true) {
+      return Ok(());
+    }
+
+    let storage_ids = self.graph.get_list_attr::<usize>("storage_id")?;
+    let dtypes = self
+      .tensors
+      .iter()
+      .map(|tensor| tensor.dtype)
+      .collect::<Vec<DataType>>();
+    let storage_num_bytes = storage_num_bytes(&storage_ids, &new_shapes, &dtypes);
+    let align = Self::storage_align(&dtypes);
+    let mut is_reallocated = vec![false; storage_num_bytes.len()];
+    for (eid, &storage_id) in storage_ids.iter().enumerate() {
+      is_reallocated[storage_id] |= is_reshaped[eid];
+    }
+    // The first `Tensor` of each `storage_id` owns the `Storage`. Unused `storage_id`s have none.
+    let owner_eids = (0..storage_num_bytes.len())
+      .map(|storage_id| storage_ids.iter().position(|&sid| sid == storage_id))
+      .collect::<Vec<Option<usize>>>();
+
+    // The check runs on scratch storage so that a rejected shape can't overwrite the inputs,
+    // params and outputs of the executor. The scratch storage of the reshaped entries is kept.
+    let scratch_storages = owner_eids
+      .iter()
+      .zip(storage_num_bytes.iter())
+      .map(|(owner_eid, &nbytes)| match owner_eid {
+        Some(_) => {
+          let storage = Storage::new(nbytes, align)?;
+          unsafe { ptr::write_bytes(storage.as_mut_ptr(), 0, nbytes) };
+          Ok(storage)
+        }
+        None => Ok(Storage::View(&mut [], 1)),
+      }).collect::<Result<Vec<Storage<'t>>>>()?;
+    let mut scratch_tensors = Self::tensors_from_storages(
+      scratch_storages,
+      storage_ids.clone(),
+      new_shapes.clone(),
+      dtypes.clone(),
+    );
+    self
+      .op_execs
+      .iter_mut()
+      .for_each(|op_exec| op_exec.bind(&scratch_tensors));
+    if let Err(err) = self.op_execs.iter().try_for_each(OpExec::run) {
+      let tensors = &self.tensors;
+      self
+        .op_execs
+        .iter_mut()
+        .for_each(|op_exec| op_exec.bind(tensors));
+      bail!("The ops do not accept the new shapes: {}", err);
+    }
+
+    let storages = owner_eids
+      .iter()
+      .enumerate()
+      .map(|(storage_id, owner_eid)| match *owner_eid {
+        Some(eid) if is_reallocated[storage_id] => {
+          mem::replace(&mut scratch_tensors[eid].data, Storage::View(&mut [], 1))
         }
-      }).collect::<Result<Vec<DataType>>>()?;
+        Some(eid) => self.tensors[eid].data.view(),
+        None => Storage::View(&mut [], 1),
+      }).collect::<Vec<Storage<'t>>>();
+    let mut tensors =
+      Self::tensors_from_storages(storages, storage_ids, new_shapes.clone(), dtypes);
+    // unchanged storage is moved from the existing owner to the new one
+    for (storage_id, owner_eid) in owner_eids.into_iter().enumerate() {
+      if let Some(eid) = owner_eid.filter(|_| !is_reallocated[storage_id]) {
+        mem::swap(&mut tensors[eid].data, &mut self.tensors[eid].data);
+      }
+    }
+    self.tensors = tensors;
+    let tensors = &self.tensors;
+    self
+      .op_execs
+      .iter_mut()
+      .for_each(|op_exec| op_exec.bind(tensors));
+
+    let graph = &self.graph;
+    self.shared_param_names.retain(|name| {
+      graph
+        .arg_entry_index(name)
+        .map_or(false, |eid| !is_reshaped[eid])
+    });
+    self.graph.attrs.as_mut().unwrap().insert(
+      "shape".to_string(),
+      serde_json::to_value(("list_shape", new_shapes))?,
+    );
+    Ok(())
+  }
+
+  /// Returns the entry index of the graph input named `name` or, if `name` is of the form
+  /// `node_name:index`, of output `index` of the node named `node_name`.
+  fn entry_index_by_name(&self, name: &str) -> Result<usize> {
+    if let Some(eid) = self.get_input_index(name) {
+      return Ok(eid);
+    }
+    let mut parts = name.rsplitn(2, ':');
+    match (parts.next().map(str::parse::<usize>), parts.next()) {
+      (Some(Ok(index)), Some(node_name)) => self.node_entry_index(node_name, index),
+      _ => bail!("Unknown input or node output `{}`", name),
+    }
+  }
+
+  /// Runs the ops of the nodes with indices in `start..end`.
+  /// The inputs of those ops must have been set or computed by a previous run.
+  pub fn run_range(&self, start: usize, end: usize) -> Result<()> {
+    ensure!(
+      start <= end && end <= self.graph.nodes.len(),
+      "Invalid node range {}..{} for graph with {} nodes",
+      start,
+      end,
+      self.graph.nodes.len()
+    );
+    self
+      .op_execs
+      .iter()
+      .filter(|op_exec| start <= op_exec.node_id && op_exec.node_id < end)
+      .try_for_each(OpExec::run)
+  }
+
+  /// Runs the ops up to and including that of the node named `node_name`.
+  pub fn run_until<S: AsRef<str>>(&self, node_name: S) -> Result<()> {
+    let end = self.node_index_or_err(node_name.as_ref())? + 1;
+    self.run_range(0, end)
+  }
+
+  /// Runs the ops starting from that of the node named `node_name`, such as after its inputs
+  /// have been set using `set_node_output`.
+  pub fn run_from<S: AsRef<str>>(&self, node_name: S) -> Result<()> {
+    let start = self.node_index_or_err(node_name.as_ref())?;
+    self.run_range(start, self.graph.nodes.len())
+  }
+
+  /// Returns the index of the node named `node_name`, if it exists.
+  pub fn node_index<S: AsRef<str>>(&self, node_name: S) -> Option<usize> {
+    self
+      .graph
+      .nodes
+      .iter()
+      .position(|node| node.name == node_name.as_ref())
+  }
+
+  fn node_index_or_err(&self, node_name: &str) -> Result<usize> {
+    Ok(
+      self
+        .node_index(node_name)
+        .ok_or(format!("Unknown node `{}`", node_name))?,
+    )
+  }
+
+  /// Returns the entry index of output `index` of the node named `node_name`.
+  fn node_entry_index(&self, node_name: &str, index: usize) -> Result<usize> {
+    let id = self.node_index_or_err(node_name)?;
+    let node_row_ptr = self.graph.node_row_ptr.as_ref().unwrap();
+    ensure!(
+      index < node_row_ptr[id + 1] - node_row_ptr[id],
+      "Node `{}` has no output {}",
+      node_name,
+      index
+    );
+    Ok(node_row_ptr[id] + index)
+  }
+
+  /// Returns output `index` of the node named `node_name`.
+  ///
+  /// The output of an op is only valid until another op reuses its storage.
+  pub fn get_node_output<S: AsRef<str>>(&self, node_name: S, index: usize) -> Result<&Tensor> {
+    let idx = self.node_entry_index(node_name.as_ref(), index)?;
+    Ok(&self.tensors[idx])
+  }
+
+  /// Copies `value` into output `index` of the node named `node_name` so that the ops which
+  /// consume it can be run using `run_from` or `run_range`. Setting the output of an input node
+  /// is equivalent to `set_input`.
+  pub fn set_node_output<S: AsRef<str>>(
+    &mut self,
+    node_name: S,
+    index: usize,
+    value: Tensor,
+  ) -> Result<()> {
+    let idx = self.node_entry_index(node_name.as_ref(), index)?;
+    let tensor = &self.tensors[idx];
+    ensure!(
+      tensor.dtype == value.dtype && tensor.size == value.size,
+      "Output {} of node `{}` has dtype {} and {} elements but value has dtype {} and {} elements",
+      index,
+      node_name.as_ref(),
+      tensor.dtype,
+      tensor.size,
+      value.dtype,
+      value.size
+    );
+    ensure!(
+      value.is_contiguous(),
+      "Cannot set output {} of node `{}` to a non-contiguous tensor",
+      index,
+      node_name.as_ref()
+    );
+    self.unshare_input(node_name.as_ref());
+    self.tensors[idx].copy(&value);
+    Ok(())
+  }
+
+  /// Runs the computation graph and returns a copy of each op's outputs taken immediately
+  /// after the op ran.
+  pub fn run_debug(&self) -> Result<DebugDump> {
+    let node_row_ptr = self.graph.node_row_ptr.as_ref().unwrap();
+    let mut dump = DebugDump::default();
+    for op_exec in self.op_execs.iter() {
+      op_exec.run()?;
+      let node_id = op_exec.node_id;
+      for (index, eid) in (node_row_ptr[node_id]..node_row_ptr[node_id + 1]).enumerate() {
+        dump.record(
+          &self.graph.nodes[node_id].name,
+          index,
+          self.tensors[eid].to_owned(),
+        );
+      }
+    }
+    Ok(dump)
+  }
 
-    let align = dtypes.iter().map(|dtype| dtype.bits as usize).max();
-    let mut storage_num_bytes = vec![0usize; *storage_ids.iter().max().unwrap_or(&1) + 1];
-    for (i, &storage_id) in storage_ids.iter().enumerate() {
-      let dtype_size = dtypes[i].bits * dtypes[i].lanes >> 3;
-      let nbytes = dtype_size * shapes[i].iter().product::<i64>() as usize;
-      storage_num_bytes[storage_id] = cmp::max(nbytes, storage_num_bytes[storage_id]);
+  /// Runs the computation graph and records the time taken by each op in `profiler`.
+  /// Profiling is unavailable on targets without a clock, such as wasm32 and SGX.
+  #[cfg(not(any(target_arch = "wasm32", target_env = "sgx")))]
+  pub fn run_profiled(&self, profiler: &mut Profiler) -> Result<()> {
+    for op_exec in self.op_execs.iter() {
+      let start = Instant::now();
+      op_exec.run()?;
+      profiler.record(&self.graph.nodes[op_exec.node_id], start.elapsed());
     }
+    Ok(())
+  }
+
+  /// Allocates `Storages` for each `storage_id` and returns `Tensor`s to hold each output.
+  /// Params which can be bound without copying are removed from `params` and their
+  /// `Storage`s are used in place of newly allocated ones.
+  fn setup_storages<'a>(
+    graph: &'a Graph,
+    params: &mut HashMap<String, Tensor<'t>>,
+  ) -> Result<Vec<Tensor<'t>>> {
+    let storage_ids = graph.get_list_attr::<usize>("storage_id")?;
+    let shapes = graph.shapes()?;
+    let dtypes = graph.dtypes()?;
+
+    let align = Self::storage_align(&dtypes);
+    let storage_num_bytes = storage_num_bytes(&storage_ids, &shapes, &dtypes);
+
+    let bindable_names = params
+      .iter()
+      .filter(|(name, param)| {
+        graph.arg_entry_index(name).map_or(false, |idx| {
+          let storage_id = storage_ids[idx];
+          param.dtype == dtypes[idx]
+            && param.size == shapes[idx].iter().product::<i64>() as usize
+            && param.is_contiguous()
+            && param.byte_offset == 0
+            && param.data.size() >= storage_num_bytes[storage_id]
+            && param.data.as_ptr() as usize % param.dtype.align() == 0
+            && storage_ids.iter().filter(|&&sid| sid == storage_id).count() == 1
+        })
+      }).map(|(name, _)| name.clone())
+      .collect::<Vec<String>>();
+    let mut bound_storages = bindable_names
+      .into_iter()
+      .map(|name| {
+        let storage_id = storage_ids[graph.arg_entry_index(&name).unwrap()];
+        (storage_id, params.remove(&name).unwrap().data)
+      }).collect::<HashMap<usize, Storage<'t>>>();
 
-    let mut storages: Vec<Storage> = storage_num_bytes
+    let storages: Vec<Storage<'t>> = storage_num_bytes
       .into_iter()
-      .map(|nbytes| Storage::new(nbytes, align))
-      .collect::<Result<Vec<Storage>>>()?;
+      .enumerate()
+      .map(|(storage_id, nbytes)| match bound_storages.remove(&storage_id) {
+        Some(storage) => Ok(storage),
+        None => Storage::new(nbytes, align),
+      }).collect::<Result<Vec<Storage<'t>>>>()?;
+
+    Ok(Self::tensors_from_storages(
+      storages,
+      storage_ids,
+      shapes,
+      dtypes,
+    ))
+  }
+
+  fn storage_align(dtypes: &[DataType]) -> Option<usize> {
+    dtypes.iter().map(|dtype| dtype.bits as usize).max()
+  }
 
-    let tensors = izip!(storage_ids, shapes, dtypes)
+  /// Returns a `Tensor` for each entry which views the `Storage` of its `storage_id`.
+  /// The first `Tensor` of each `storage_id` takes ownership of the `Storage`.
+  fn tensors_from_storages(
+    mut storages: Vec<Storage<'t>>,
+    storage_ids: Vec<usize>,
+    shapes: Vec<Vec<i64>>,
+    dtypes: Vec<DataType>,
+  ) -> Vec<Tensor<'t>> {
+    izip!(storage_ids, shapes, dtypes)
       .map(|(storage_id, shape, dtype)| {
         let storage = storages[storage_id].view();
         Tensor {
@@ -216,17 +1211,15 @@ impl<'m, 't> GraphExecutor<'m, 't> {
           strides: None,
           byte_offset: 0,
         }
-      }).collect();
-
-    Ok(tensors)
+      }).collect()
   }
 
-  /// Creates closures which represent the computation performed by this graph.
+  /// Creates `OpExec`s which represent the computation performed by this graph.
   fn setup_op_execs<M: 'm + Module>(
     graph: &Graph,
     lib: &'m M,
     tensors: &Vec<Tensor<'t>>,
-  ) -> Result<Vec<Box<Fn() + 'm>>> {
+  ) -> Result<Vec<OpExec>> {
     ensure!(graph.node_row_ptr.is_some(), "Missing node_row_ptr.");
     let node_row_ptr = graph.node_row_ptr.as_ref().unwrap();
 
@@ -251,114 +1244,256 @@ impl<'m, 't> GraphExecutor<'m, 't> {
         .inputs
         .iter()
         .map(|entry| graph.entry_index(entry))
-        .chain((0..attrs.num_outputs).map(|oi| Ok(node_row_ptr[i].clone() + oi)));
-
-      let dl_tensors = arg_indices
-        .map(|idx| {
-          let tensor = &tensors[idx?];
-          Ok(if attrs.flatten_data {
-            DLTensor::from_tensor(tensor, true /* flatten */)
-          } else {
-            DLTensor::from(tensor)
-          })
-        }).collect::<Result<Vec<DLTensor>>>()
-        .unwrap();
-      let op: Box<Fn()> = box move || {
-        let args = dl_tensors
-          .iter()
-          .map(|t| t.into())
-          .collect::<Vec<TVMArgValue>>();
-        func(args.as_slice());
+        .chain((0..attrs.num_outputs).map(|oi| Ok(node_row_ptr[i].clone() + oi)))
+        .collect::<Result<Vec<usize>>>()?;
+
+      let mut op_exec = OpExec {
+        node_id: i,
+        node_name: node.name.clone(),
+        func_name: attrs.func_name,
+        func: func,
+        arg_indices: arg_indices,
+        flatten_data: attrs.flatten_data,
+        dl_tensors: Vec::new(),
       };
-      op_execs.push(op);
+      op_exec.bind(tensors);
+      op_execs.push(op_exec);
     }
     Ok(op_execs)
   }
 
-  pub fn load_params(&mut self, params: HashMap<String, Tensor<'t>>) {
-    params.into_iter().for_each(|(name, param)| {
-      self.set_input(name, param);
-    })
-  }
-
-  pub fn set_input<S: AsRef<str>>(&mut self, name: S, value: Tensor<'t>) {
-    if let Some(idx) = self.get_input_index(name.as_ref()) {
-      // TODO: consider `new_with_params` to avoid ever allocating
-      let ptr = self.tensors[idx].data.as_ptr();
-      let mut to_replace = self.tensors.iter_mut().filter(|t| t.data.as_ptr() == ptr);
-      let mut owner = to_replace.nth(0).unwrap();
-      if value.data.is_owned() {
-        // FIXME: for no-copy, need setup_op_execs to not capture tensor ptr
-        // mem::replace(&mut (*owner), value);
-        // to_replace.for_each(|t| {
-        //   panic!("replacing");
-        //   t.data = owner.data.view();
-        // });
-        owner.copy(&value);
-      } else {
-        owner.copy(&value);
+  /// Copies `params` into the graph inputs of the same names and reports the params which
+  /// are unknown, missing, or differ from the graph's `shape` and `dltype` attrs.
+  ///
+  /// With `ParamCheck::Strict`, nothing is loaded if any param is unknown or mismatched and
+  /// the error describes the report. With `ParamCheck::Lenient`, the params whose dtype and
+  /// size match those of their input are loaded.
+  pub fn load_params(
+    &mut self,
+    params: HashMap<String, Tensor<'t>>,
+    check: ParamCheck,
+  ) -> Result<ParamReport> {
+    let report = self.check_params(&params)?;
+    ensure!(
+      check == ParamCheck::Lenient || report.is_ok(),
+      ErrorKind::LoadGraphParamsError(report.to_string())
+    );
+    for (name, param) in params.into_iter() {
+      if report.loaded.contains(&name) {
+        self.param_names.insert(name.clone());
+        self.set_input(name, param)?;
       }
-    } else {
-      println!("Unexpected input `{}`", name.as_ref());
     }
+    Ok(report)
   }
 
-  /// Returns the graph input with name `name`, if it exists.
-  pub fn get_input<S: AsRef<str>>(&mut self, name: S) -> Option<&Tensor> {
-    self
-      .get_input_index(name.as_ref())
-      .and_then(move |idx| Some(&self.tensors[idx]))
+  /// Compares `params` against the graph inputs as in `load_params` without loading them.
+  pub fn check_params(&self, params: &HashMap<String, Tensor>) -> Result<ParamReport> {
+    Self::param_report(&self.graph, params)
   }
 
-  /// Returns the graph output with index `index`, if it exists.
-  pub fn get_output(&self, idx: usize) -> Option<&Tensor> {
-    let graph = &self.graph;
-    graph.heads.get(idx).and_then(|entry| {
-      graph
-        .entry_index(entry)
-        .map(|idx| self.tensors.get(idx))
-        .unwrap_or(None)
-    })
+  fn param_report(graph: &Graph, params: &HashMap<String, Tensor>) -> Result<ParamReport> {
+    let shapes = graph.shapes()?;
+    let dtypes = graph.dtypes()?;
+    let mut names = params.keys().collect::<Vec<&String>>();
+    names.sort();
+    let mut report = ParamReport::default();
+    for name in names.into_iter() {
+      let eid = match graph.arg_entry_index(name) {
+        Some(eid) => eid,
+        None => {
+          report.unknown.push(name.clone());
+          continue;
+        }
+      };
+      let info = |shape: &[i64], dtype: DataType| TensorInfo {
+        name: name.clone(),
+        shape: shape.to_vec(),
+        dtype: dtype,
+        num_bytes: shape.iter().product::<i64>() as usize * dtype.itemsize(),
+      };
+      let expected = info(&shapes[eid], dtypes[eid]);
+      let actual = info(&params[name].shape, params[name].dtype);
+      if expected.dtype == actual.dtype && expected.num_bytes == actual.num_bytes {
+        report.loaded.push(name.clone());
+      }
+      if expected != actual {
+        report.mismatched.push(ParamMismatch {
+          expected: expected,
+          actual: actual,
+        });
+      }
+    }
+    report.missing = graph
+      .arg_nodes
+      .iter()
+      .map(|&id| &graph.nodes[id].name)
+      .filter(|name| !params.contains_key(*name))
+      .cloned()
+      .collect();
+    Ok(report)
   }
 
-  /// Returns the index for graph input with name `name`, if it exists.
-  pub fn get_input_index<S: AsRef<str>>(&self, name: S) -> Option<usize> {
-    let graph = &self.graph;
-    (0..graph.nodes.len())
-      .skip_while(|&i| graph.nodes[i].name != name.as_ref())
-      .nth(0)
-      .and_then(|i| {
-        if graph.arg_nodes.iter().any(|&id| id == i) {
-          graph.node_row_ptr.as_ref().map(|nrp| nrp[i])
-        } else {
-          None
+  /// Loads params from a param dict in `reader`, decoding each directly into the storage
+  /// of the graph input of the same name. Params which are not graph inputs are skipped, and
+  /// the dtype and shape of the others must match those of their inputs.
+  ///
+  /// Params which were read before an error occurred remain set.
+  pub fn load_params_from<R: Read>(&mut self, reader: R) -> Result<()> {
+    let mut params = ParamReader::new(reader)?;
+    while let Some((name, header)) = params.read_header()? {
+      let idx = match self.get_input_index(&name) {
+        Some(idx) => idx,
+        None => {
+          params.skip_data(&name, &header)?;
+          continue;
         }
-      })
+      };
+      let input = &self.tensors[idx];
+      ensure!(
+        input.dtype == header.dtype && input.shape == header.shape,
+        ErrorKind::LoadGraphParamsError(format!(
+          "param `{}` has dtype {} and shape {:?} but the input has dtype {} and shape {:?}",
+          name, header.dtype, header.shape, input.dtype, input.shape
+        ))
+      );
+      self.unshare_input(&name);
+      let input = &self.tensors[idx];
+      params.read_data(&name, unsafe {
+        slice::from_raw_parts_mut(
+          input.data.as_mut_ptr().offset(input.byte_offset),
+          header.num_bytes,
+        )
+      })?;
+      self.param_names.insert(name);
+    }
+    Ok(())
   }
-}
 
-/// Converts a string to TVM DLDataTypeCode. @see `String2TVMType` in packed_func.h
-named!(
-  tvm_str_to_type<CompleteStr, DataType>,
-  do_parse!(
-    type_name: alpha1 >>
-    bits: digit1 >>
-    lanes: opt!(tuple!(tag!("x"), digit1)) >>
-    (DataType {
-      code: match type_name {
-        CompleteStr("int") => DLDataTypeCode_kDLInt,
-        CompleteStr("uint") => DLDataTypeCode_kDLUInt,
-        CompleteStr("float") => DLDataTypeCode_kDLFloat,
-        _ => DLDataTypeCode_kDLFloat,
-      } as usize,
-      bits: bits.parse::<u8>().unwrap() as usize,
-      lanes: match lanes {
-        Some(lanes) => lanes.1.parse::<u16>().unwrap() as usize,
-        None => 1,
-      },
+  /// Returns descriptions of the graph inputs which have not been set from a param dict.
+  pub fn inputs(&self) -> Vec<TensorInfo> {
+    self.arg_infos(false)
+  }
+
+  /// Returns descriptions of the graph inputs which have been set from a param dict.
+  pub fn params(&self) -> Vec<TensorInfo> {
+    self.arg_infos(true)
+  }
+
+  fn arg_infos(&self, params: bool) -> Vec<TensorInfo> {
+    let node_row_ptr = self.graph.node_row_ptr.as_ref().unwrap();
+    self
+      .graph
+      .arg_nodes
+      .iter()
+      .map(|&id| (&self.graph.nodes[id].name, node_row_ptr[id]))
+      .filter(|&(name, _)| self.param_names.contains(name) == params)
+      .map(|(name, eid)| self.tensor_info(name.clone(), eid))
+      .collect()
+  }
+
+  /// Returns descriptions of the graph outputs in the order used by `get_output`.
+  pub fn outputs(&self) -> Vec<TensorInfo> {
+    self
+      .graph
+      .heads
+      .iter()
+      .map(|entry| {
+        self.tensor_info(
+          format!("{}:{}", self.graph.nodes[entry.id].name, entry.index),
+          self.graph.entry_index(entry).unwrap(),
+        )
+      }).collect()
+  }
+
+  fn tensor_info(&self, name: String, eid: usize) -> TensorInfo {
+    let tensor = &self.tensors[eid];
+    TensorInfo {
+      name: name,
+      shape: tensor.shape.clone(),
+      dtype: tensor.dtype,
+      num_bytes: tensor.size * tensor.dtype.itemsize(),
+    }
+  }
+
+  /// Copies `value` into the graph input named `name`.
+  ///
+  /// Fails if there is no such input or if `value` is not a contiguous tensor of the dtype and
+  /// size of the input.
+  pub fn set_input<S: AsRef<str>>(&mut self, name: S, value: Tensor<'t>) -> Result<()> {
+    let name = name.as_ref();
+    let idx = match self.get_input_index(name) {
+      Some(idx) => idx,
+      None => bail!("Unexpected input `{}`", name),
+    };
+    let input = &self.tensors[idx];
+    ensure!(
+      input.dtype == value.dtype && input.size == value.size,
+      "Input `{}` has dtype {} and shape {:?} but value has dtype {} and shape {:?}",
+      name,
+      input.dtype,
+      input.shape,
+      value.dtype,
+      value.shape
+    );
+    ensure!(
+      value.is_contiguous(),
+      "Cannot set input `{}` to a non-contiguous tensor",
+      name
+    );
+    self.unshare_input(name);
+    // `setup_op_execs` captures pointers to the existing storage, so the value must be copied.
+    // Use `new_with_params` to bind params without copying.
+    let ptr = self.tensors[idx].data.as_ptr();
+    let mut to_replace = self.tensors.iter_mut().filter(|t| t.data.as_ptr() == ptr);
+    let owner = to_replace.nth(0).unwrap();
+    owner.copy(&value);
+    Ok(())
+  }
+
+  /// Gives the executor its own copy of the input named `name` if it is bound to read-only
+  /// storage so that the input can be written.
+  fn unshare_input(&mut self, name: &str) {
+    if let Some(idx) = self.get_input_index(name) {
+      if self.shared_param_names.remove(name) {
+        self.tensors[idx].data = self.tensors[idx].data.to_owned();
+        let tensors = &self.tensors;
+        self
+          .op_execs
+          .iter_mut()
+          .for_each(|op_exec| op_exec.bind(tensors));
+      }
+    }
+  }
+
+  /// Returns the graph input with name `name`, if it exists.
+  pub fn get_input<S: AsRef<str>>(&mut self, name: S) -> Option<&Tensor> {
+    self
+      .get_input_index(name.as_ref())
+      .and_then(move |idx| Some(&self.tensors[idx]))
+  }
+
+  /// Returns the graph output with index `index`, if it exists.
+  pub fn get_output(&self, idx: usize) -> Option<&Tensor> {
+    let graph = &self.graph;
+    graph.heads.get(idx).and_then(|entry| {
+      graph
+        .entry_index(entry)
+        .map(|idx| self.tensors.get(idx))
+        .unwrap_or(None)
     })
-  )
-);
+  }
+
+  /// Returns the index for graph input with name `name`, if it exists.
+  pub fn get_input_index<S: AsRef<str>>(&self, name: S) -> Option<usize> {
+    self.graph.arg_entry_index(name.as_ref())
+  }
+}
+
+/// Escapes `"` and `\` for use in a quoted DOT string.
+fn escape_dot(s: &str) -> String {
+  s.replace('\\', "\\\\").replace('"', "\\\"")
+}
 
 /// Converts a bytes to String.
 named!(
@@ -389,35 +1524,91 @@ named!(
   )
 );
 
-/// Parses a Tensor from a TVM array file.
+/// Parses the fixed-size header which precedes the shape and data of a TVM array file.
 named!(
-  tensor<Tensor>,
+  tensor_header<&[u8], (u64, u64, TVMContext, u32, DataType)>,
+  tuple!(le_u64, le_u64, tvm_ctx, le_u32, data_type)
+);
+
+/// The length of the header which precedes the shape and data of a TVM array file.
+pub(super) const TENSOR_HEADER_LEN: usize = 32;
+
+/// Parses and validates the header which precedes the shape and data of a TVM array file,
+/// returning its context, number of dimensions, and dtype, or else why the header is invalid.
+pub(super) fn parse_tensor_header(
+  bytes: &[u8],
+) -> ::std::result::Result<(TVMContext, u32, DataType), String> {
+  let (magic, reserved, ctx, ndim, dtype) = match tensor_header(bytes) {
+    Ok((_, header)) => header,
+    Err(_) => return Err("truncated header".to_string()),
+  };
+  if magic != NDARRAY_MAGIC {
+    Err(format!("invalid magic number {:#x}", magic))
+  } else if reserved != 0 {
+    Err(format!("invalid reserved field {:#x}", reserved))
+  } else if ctx.device_type != DLDeviceType_kDLCPU as usize {
+    Err(format!("unsupported context {:?}", ctx))
+  } else if !dtype.is_valid() {
+    Err(format!("invalid dtype {:?}", dtype))
+  } else {
+    Ok((ctx, ndim, dtype))
+  }
+}
+
+/// Returns the number of elements of a tensor of `shape`, or `None` if a dimension is
+/// negative or the number overflows.
+pub(super) fn num_elements(shape: &[i64]) -> Option<usize> {
+  shape.iter().try_fold(1usize, |size, &dim| {
+    usize::try_from(dim)
+      .ok()
+      .and_then(|dim| size.checked_mul(dim))
+  })
+}
+
+/// Parses the context, dtype, shape, number of elements, and data of a TVM array file.
+named!(
+  tensor_fields<(TVMContext, DataType, Vec<i64>, usize, &[u8])>,
   do_parse!(
-    take!(8)
+    verify!(le_u64, |magic| magic == NDARRAY_MAGIC)
       >> bits!(tag_bits!(u64, 64, 0))
       >> ctx: tvm_ctx
       >> ndim: le_u32
       >> dtype: data_type
-      >> shape: count!(map!(le_i64, |sz| sz as i64), ndim as usize)
+      >> shape: map_opt!(count!(le_i64, ndim as usize), |shape: Vec<i64>| {
+        num_elements(&shape).map(|size| (shape, size))
+      })
       >> length: le_i64
       >> data: take!(length)
-      >> (Tensor {
+      >> ((ctx, dtype, shape.0, shape.1, data))
+  )
+);
+
+/// Parses a Tensor from a TVM array file whose data is as long as its shape and dtype require.
+named!(
+  tensor<Tensor>,
+  map_opt!(tensor_fields, |fields: (TVMContext, DataType, Vec<i64>, usize, &[u8])| {
+    let (ctx, dtype, shape, size, data) = fields;
+    if size.checked_mul(dtype.itemsize()) == Some(data.len()) {
+      Some(Tensor {
         data: Storage::from(data),
         ctx: ctx,
         dtype: dtype,
-        size: shape.iter().product::<i64>() as usize,
+        size: size,
         shape: shape,
         strides: None,
         byte_offset: 0,
       })
-  )
+    } else {
+      None
+    }
+  })
 );
 
 /// Parses a graph params dict from a params binary file.
 named!(
   parse_param_dict<HashMap<String, Tensor>>,
   do_parse!(
-    take!(8)
+    verify!(le_u64, |magic| magic == NDARRAY_LIST_MAGIC)
       >> bits!(tag_bits!(u64, 64, 0))
       >> names: length_count!(le_u64, name)
       >> tensors: length_count!(le_u64, tensor)
@@ -440,27 +1631,1113 @@ pub fn load_param_dict(bytes: &[u8]) -> Result<HashMap<String, Tensor>> {
   }
 }
 
+impl<'a> Tensor<'a> {
+  /// Loads a single `Tensor` saved using TVM's `NDArray::Save` or `Tensor::save_ndarray`.
+  /// The returned `Tensor` borrows its data from `bytes`.
+  pub fn load_ndarray(bytes: &'a [u8]) -> Result<Tensor<'a>> {
+    parse_tensor_header(bytes).map_err(ErrorKind::LoadTensorError)?;
+
+    match tensor_fields(bytes) {
+      Ok((remaining_bytes, (ctx, dtype, shape, size, data))) => {
+        ensure!(
+          remaining_bytes.len() == 0,
+          ErrorKind::LoadTensorError("extra input".to_string())
+        );
+        let num_bytes = size.checked_mul(dtype.itemsize()).ok_or_else(|| {
+          ErrorKind::LoadTensorError(format!("invalid shape {:?} for dtype {}", shape, dtype))
+        })?;
+        ensure!(
+          data.len() == num_bytes,
+          ErrorKind::LoadTensorError(format!(
+            "expected {} bytes of data but found {}",
+            num_bytes,
+            data.len()
+          ))
+        );
+        Ok(Tensor {
+          data: Storage::from(data),
+          ctx: ctx,
+          dtype: dtype,
+          size: size,
+          shape: shape,
+          strides: None,
+          byte_offset: 0,
+        })
+      }
+      Err(_) => bail!(ErrorKind::LoadTensorError(
+        "invalid shape or truncated data".to_string()
+      )),
+    }
+  }
+
+  /// Serializes this `Tensor` into the format read by `Tensor::load_ndarray`.
+  pub fn save_ndarray(&self) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    put_tensor(&mut buf, self)?;
+    Ok(buf)
+  }
+}
+
+/// Appends the `num_bytes` low-order bytes of `val` to `buf` in little-endian order.
+pub(super) fn put_le(buf: &mut Vec<u8>, val: u64, num_bytes: usize) {
+  buf.extend((0..num_bytes).map(|i| (val >> (8 * i)) as u8));
+}
+
+/// Serializes a string as its length followed by its bytes.
+fn put_name(buf: &mut Vec<u8>, name: &str) {
+  put_le(buf, name.len() as u64, 8);
+  buf.extend_from_slice(name.as_bytes());
+}
+
+/// Serializes a `Tensor` in the TVM array format read by the `tensor` parser.
+/// @see `SaveDLTensor` in `ndarray.cc`.
+fn put_tensor(buf: &mut Vec<u8>, tensor: &Tensor) -> Result<()> {
+  ensure!(tensor.is_contiguous(), "Cannot save a non-contiguous tensor");
+  put_le(buf, NDARRAY_MAGIC, 8);
+  put_le(buf, 0, 8); // reserved
+  put_le(buf, tensor.ctx.device_type as u64, 4);
+  put_le(buf, tensor.ctx.device_id as u64, 4);
+  put_le(buf, tensor.shape.len() as u64, 4);
+  put_le(buf, tensor.dtype.code as u64, 1);
+  put_le(buf, tensor.dtype.bits as u64, 1);
+  put_le(buf, tensor.dtype.lanes as u64, 2);
+  tensor
+    .shape
+    .iter()
+    .for_each(|&dim| put_le(buf, dim as u64, 8));
+  let num_bytes = tensor.size * tensor.dtype.itemsize();
+  ensure!(
+    tensor.byte_offset >= 0 && tensor.byte_offset as usize + num_bytes <= tensor.data.size(),
+    "Cannot save {} bytes at offset {} of a tensor with {} bytes of data",
+    num_bytes,
+    tensor.byte_offset,
+    tensor.data.size()
+  );
+  put_le(buf, num_bytes as u64, 8);
+  buf.extend_from_slice(unsafe {
+    slice::from_raw_parts(tensor.data.as_ptr().offset(tensor.byte_offset), num_bytes)
+  });
+  Ok(())
+}
+
+/// Serializes a param dict into the format produced by `nnvm.compiler.save_param_dict`.
+/// Params are written in order of name so that the output is deterministic.
+pub fn save_param_dict<'a>(params: &HashMap<String, Tensor<'a>>) -> Result<Vec<u8>> {
+  let mut names = params.keys().collect::<Vec<&String>>();
+  names.sort();
+
+  let mut buf = Vec::new();
+  put_le(&mut buf, NDARRAY_LIST_MAGIC, 8);
+  put_le(&mut buf, 0, 8); // reserved
+  put_le(&mut buf, names.len() as u64, 8);
+  names.iter().for_each(|name| put_name(&mut buf, name));
+  put_le(&mut buf, names.len() as u64, 8);
+  for name in names.iter() {
+    put_tensor(&mut buf, &params[*name])?;
+  }
+  Ok(buf)
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+  use ndarray::{Array, ArrayD};
+
   use super::*;
+  use ffi::runtime::{DLDataTypeCode_kDLFloat, DLDataTypeCode_kDLInt, DLDataTypeCode_kDLUInt};
+  use runtime::{
+    register_global, CompositeModule, FuncModule, GraphBuilder, IntoPackedFunc, TVMRetValue,
+  };
+
+  pub(crate) const TEST_GRAPH_JSON: &str = r#"{
+    "nodes": [
+      {"op": "null", "name": "x", "inputs": []},
+      {"op": "null", "name": "w", "inputs": []},
+      {
+        "op": "tvm_op",
+        "name": "mul",
+        "attrs": {
+          "func_name": "mul",
+          "num_inputs": "2",
+          "num_outputs": "1",
+          "flatten_data": "0"
+        },
+        "inputs": [[0, 0, 0], [1, 0, 0]]
+      },
+      {
+        "op": "tvm_op",
+        "name": "add_one",
+        "attrs": {
+          "func_name": "add_one",
+          "num_inputs": "1",
+          "num_outputs": "1",
+          "flatten_data": "1"
+        },
+        "inputs": [[2, 0, 0]]
+      }
+    ],
+    "arg_nodes": [0, 1],
+    "heads": [[3, 0, 0]],
+    "node_row_ptr": [0, 1, 2, 3, 4],
+    "attrs": {
+      "storage_id": ["list_int", [0, 1, 2, 3]],
+      "shape": ["list_shape", [[2, 2], [2, 2], [2, 2], [2, 2]]],
+      "dltype": ["list_str", ["float32", "float32", "float32", "float32"]]
+    }
+  }"#;
+
+  /// `TEST_GRAPH_JSON` in the layout of the Relay-based graph runtime.
+  const TEST_RELAY_GRAPH_JSON: &str = r#"{
+    "nodes": [
+      {"op": "null", "name": "x", "inputs": []},
+      {"op": "null", "name": "w", "inputs": []},
+      {
+        "op": "tvm_op",
+        "name": "mul",
+        "attrs": {
+          "func_name": "mul",
+          "num_inputs": "2",
+          "num_outputs": "1",
+          "hash": "5a0e2e1b8c3d4f67"
+        },
+        "inputs": [[0, 0, 0], [1, 0, 0]]
+      },
+      {
+        "op": "tvm_op",
+        "name": "add_one",
+        "attrs": {
+          "func_name": "add_one",
+          "num_inputs": 1,
+          "num_outputs": 1,
+          "flatten_data": "1",
+          "hash": "0b1f2a3c4d5e6f70"
+        },
+        "inputs": [[2, 0, 0]]
+      }
+    ],
+    "arg_nodes": [0, 1],
+    "heads": [[3, 0, 0]],
+    "node_row_ptr": [0, 1, 2, 3, 4],
+    "attrs": {
+      "storage_id": ["list_int", [0, 1, 2, 3]],
+      "shape": ["list_shape", [[2, 2], [2, 2], [2, 2], [2, 2]]],
+      "dltype": ["list_str", ["float32", "float32", "float32", "float32"]],
+      "device_index": ["list_int", [1, 1, 1, 1]],
+      "storage_scope": ["list_str", ["global", "global", "global", "global"]]
+    },
+    "metadata": {"version": "0.0.1"}
+  }"#;
+
+  /// `TEST_GRAPH_JSON` in the layout of older NNVM versions.
+  const TEST_LEGACY_GRAPH_JSON: &str = r#"{
+    "nodes": [
+      {"op": "null", "name": "x", "inputs": []},
+      {"op": "null", "name": "w", "inputs": []},
+      {
+        "op": "tvm_op",
+        "name": "mul",
+        "attr": {"func_name": "mul", "num_inputs": "2", "num_outputs": "1", "flatten_data": "0"},
+        "inputs": [[0, 0], [1, 0]]
+      },
+      {
+        "op": "tvm_op",
+        "name": "add_one",
+        "attr": {"func_name": "add_one", "num_inputs": "1", "num_outputs": "1", "flatten_data": "1"},
+        "inputs": [[2, 0]]
+      }
+    ],
+    "arg_nodes": [0, 1],
+    "heads": [[3, 0]],
+    "attrs": {
+      "storage_id": [0, 1, 2, 3],
+      "shape": [[2, 2], [2, 2], [2, 2], [2, 2]],
+      "dltype": ["float32", "float32", "float32", "float32"]
+    }
+  }"#;
+
+  fn test_graph() -> Graph {
+    Graph::try_from(TEST_GRAPH_JSON).unwrap()
+  }
+
+  /// Returns the `f32` data of a `DLTensor` passed to a packed function.
+  unsafe fn arg_data<'a>(arg: &TVMArgValue) -> &'a mut [f32] {
+    let tensor = &*(arg.value.v_handle as *const DLTensor);
+    let size = (0..tensor.ndim as usize)
+      .map(|i| *tensor.shape.offset(i as isize))
+      .product::<i64>();
+    slice::from_raw_parts_mut(tensor.data as *mut f32, size as usize)
+  }
+
+  /// A `Module` which provides the functions used by `TEST_GRAPH_JSON`.
+  pub(crate) struct TestModule;
+
+  impl Module for TestModule {
+    fn get_function<S: AsRef<str>>(&self, name: S) -> Option<PackedFunc> {
+      match name.as_ref() {
+        "mul" => Some(box |args: &[TVMArgValue]| {
+          let (a, b, out) = unsafe { (arg_data(&args[0]), arg_data(&args[1]), arg_data(&args[2])) };
+          izip!(a.iter(), b.iter(), out.iter_mut()).for_each(|(a, b, out)| *out = a * b);
+          Ok(TVMRetValue::default())
+        }),
+        "add_one" => Some(box |args: &[TVMArgValue]| {
+          // copy the input since it may share storage with the output
+          let a = unsafe { arg_data(&args[0]).to_vec() };
+          let out = unsafe { arg_data(&args[1]) };
+          izip!(a.iter(), out.iter_mut()).for_each(|(a, out)| *out = a + 1.);
+          Ok(TVMRetValue::default())
+        }),
+        _ => None,
+      }
+    }
+  }
+
+  /// Asserts that `graph` fails validation with a message containing `reason`.
+  fn assert_invalid_graph(graph: Graph, reason: &str) {
+    match graph.validate() {
+      Err(Error(ErrorKind::GraphFormatError(msg), _)) => assert!(msg.contains(reason), "{}", msg),
+      Err(err) => panic!("unexpected error: {}", err),
+      Ok(()) => panic!("invalid graph passed validation"),
+    }
+  }
+
+  fn dtype(code: usize, bits: usize, lanes: usize) -> DataType {
+    DataType {
+      code: code,
+      bits: bits,
+      lanes: lanes,
+    }
+  }
 
   #[test]
   fn test_str_to_type() {
     assert_eq!(
-      tvm_str_to_type(CompleteStr("float24")).unwrap().1,
-      DataType {
-        code: DLDataTypeCode_kDLFloat as usize,
-        bits: 24,
-        lanes: 1
+      "float24".parse::<DataType>().unwrap(),
+      dtype(DLDataTypeCode_kDLFloat as usize, 24, 1)
+    );
+    assert_eq!(
+      "uint111x44".parse::<DataType>().unwrap(),
+      dtype(DLDataTypeCode_kDLUInt as usize, 111, 44)
+    );
+    assert_eq!(
+      "float16".parse::<DataType>().unwrap(),
+      dtype(DLDataTypeCode_kDLFloat as usize, 16, 1)
+    );
+    assert_eq!("bfloat16".parse::<DataType>().unwrap(), dtype(4, 16, 1));
+    assert_eq!(
+      "bool".parse::<DataType>().unwrap(),
+      dtype(DLDataTypeCode_kDLUInt as usize, 1, 1)
+    );
+    assert_eq!("handle".parse::<DataType>().unwrap(), dtype(3, 64, 1));
+    assert_eq!(
+      "int".parse::<DataType>().unwrap(),
+      dtype(DLDataTypeCode_kDLInt as usize, 32, 1)
+    );
+    assert_eq!(
+      "custom[130]8x2".parse::<DataType>().unwrap(),
+      dtype(130, 8, 2)
+    );
+    assert!("custom[unregistered]8".parse::<DataType>().is_err());
+    register_global(
+      "_datatype_get_type_code",
+      (|name: String| match name.as_str() {
+        "posit" => 131,
+        "posix" => 132,
+        _ => -1,
+      }).into_packed_func(),
+      true,
+    ).unwrap();
+    assert_eq!(
+      "custom[posit]16".parse::<DataType>().unwrap(),
+      dtype(131, 16, 1)
+    );
+    assert_eq!(
+      "custom[posix]16x4".parse::<DataType>().unwrap(),
+      dtype(132, 16, 4)
+    );
+    assert!("custom[unregistered]8".parse::<DataType>().is_err());
+
+    let invalid_types = [
+      "floaty32",
+      "bool8",
+      "float256",
+      "int8x",
+      "int8x0",
+      "float0",
+      "custom[5]8",
+      "custom[",
+      "x4",
+    ];
+    for invalid in invalid_types.iter() {
+      match invalid.parse::<DataType>() {
+        Err(Error(ErrorKind::GraphFormatError(_), _)) => (),
+        _ => panic!("`{}` should be an invalid dltype", invalid),
       }
+    }
+  }
+
+  #[test]
+  fn test_type_to_str() {
+    let type_strs = [
+      "float32",
+      "int8x4",
+      "uint1x8",
+      "bool",
+      "bfloat16",
+      "handle",
+      "custom[130]8x2",
+    ];
+    for type_str in type_strs.iter() {
+      let dtype = type_str.parse::<DataType>().unwrap();
+      assert_eq!(dtype.to_string(), *type_str);
+      assert_eq!(
+        serde_json::to_string(&dtype).unwrap(),
+        format!("\"{}\"", type_str)
+      );
+      assert_eq!(
+        serde_json::from_str::<DataType>(&format!("\"{}\"", type_str)).unwrap(),
+        dtype
+      );
+    }
+    assert!(serde_json::from_str::<DataType>("\"float\"").is_ok());
+    assert!(serde_json::from_str::<DataType>("\"flaot\"").is_err());
+  }
+
+  #[test]
+  fn test_param_dict_roundtrip() {
+    let a = Array::from_shape_vec((2, 3), vec![1f32, 2., 3., 4., 5., 6.]).unwrap();
+    let b = Array::from_vec(vec![-1i32, 0, 1, 2]);
+    let mut params = HashMap::new();
+    params.insert("a".to_string(), Tensor::from(&a));
+    params.insert("b".to_string(), Tensor::from(&b));
+
+    let bytes = save_param_dict(&params).unwrap();
+    let loaded = load_param_dict(&bytes).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(
+      ArrayD::<f32>::try_from(&loaded["a"].to_owned()).unwrap(),
+      a.into_dyn()
+    );
+    assert_eq!(
+      ArrayD::<i32>::try_from(&loaded["b"].to_owned()).unwrap(),
+      b.into_dyn()
+    );
+
+    assert_eq!(save_param_dict(&loaded).unwrap(), bytes);
+  }
+
+  #[test]
+  fn test_save_param_dict_layout() {
+    let x = Array::from_vec(vec![1u32, 2]);
+    let mut params = HashMap::new();
+    params.insert("x".to_string(), Tensor::from(&x));
+
+    let mut expected = vec![0xB7, 0x9C, 0x04, 0x05, 0x4F, 0x8D, 0xE5, 0xF7];
+    expected.extend(&[0; 8]); // reserved
+    expected.extend(&[1, 0, 0, 0, 0, 0, 0, 0]); // num names
+    expected.extend(&[1, 0, 0, 0, 0, 0, 0, 0, b'x']);
+    expected.extend(&[1, 0, 0, 0, 0, 0, 0, 0]); // num tensors
+    expected.extend(&[0x3F, 0xA1, 0xB4, 0x96, 0xF0, 0x40, 0x5E, 0xDD]);
+    expected.extend(&[0; 8]); // reserved
+    expected.extend(&[1, 0, 0, 0, 0, 0, 0, 0]); // ctx
+    expected.extend(&[1, 0, 0, 0]); // ndim
+    expected.extend(&[1, 32, 1, 0]); // dtype
+    expected.extend(&[2, 0, 0, 0, 0, 0, 0, 0]); // shape
+    expected.extend(&[8, 0, 0, 0, 0, 0, 0, 0]); // num bytes
+    expected.extend(&[1, 0, 0, 0, 2, 0, 0, 0]);
+
+    assert_eq!(save_param_dict(&params).unwrap(), expected);
+  }
+
+  #[test]
+  fn test_param_dict_invalid() {
+    let x = Array::from_vec(vec![1u32, 2]);
+    let mut params = HashMap::new();
+    params.insert("x".to_string(), Tensor::from(&x));
+    let bytes = save_param_dict(&params).unwrap();
+
+    // the data length must match the shape and dtype
+    let mut short_data = bytes.clone();
+    short_data[81] = 4;
+    short_data.truncate(bytes.len() - 4);
+    assert!(load_param_dict(&short_data).is_err());
+    let mut long_data = bytes.clone();
+    long_data[81] = 12;
+    long_data.extend(&[0; 4]);
+    assert!(load_param_dict(&long_data).is_err());
+
+    params.insert(
+      "x".to_string(),
+      Tensor {
+        byte_offset: 4,
+        ..Tensor::from(&x)
+      },
+    );
+    assert!(save_param_dict(&params).is_err());
+  }
+
+  #[test]
+  fn test_ndarray_roundtrip() {
+    let a = Array::from_shape_vec((3, 2), vec![0.5f64, 1., 1.5, 2., 2.5, 3.]).unwrap();
+    let bytes = Tensor::from(&a).save_ndarray().unwrap();
+    let loaded = Tensor::load_ndarray(&bytes).unwrap();
+    assert_eq!(loaded.shape(), vec![3, 2]);
+    assert_eq!(
+      ArrayD::<f64>::try_from(&loaded.to_owned()).unwrap(),
+      a.into_dyn()
     );
+    assert_eq!(loaded.save_ndarray().unwrap(), bytes);
+  }
+
+  #[test]
+  fn test_load_ndarray_invalid() {
+    let a = Array::from_vec(vec![1f32, 2.]);
+    let bytes = Tensor::from(&a).save_ndarray().unwrap();
+
+    let assert_invalid = |bytes: &[u8], reason: &str| match Tensor::load_ndarray(bytes) {
+      Err(Error(ErrorKind::LoadTensorError(msg), _)) => assert!(msg.contains(reason), "{}", msg),
+      Err(err) => panic!("unexpected error: {}", err),
+      Ok(_) => panic!("loaded invalid tensor"),
+    };
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] ^= 1;
+    assert_invalid(&bad_magic, "magic");
+
+    let mut bad_reserved = bytes.clone();
+    bad_reserved[8] = 1;
+    assert_invalid(&bad_reserved, "reserved");
+
+    let mut bad_ctx = bytes.clone();
+    bad_ctx[16] = 2; // kDLGPU
+    assert_invalid(&bad_ctx, "context");
+
+    let mut bad_dtype = bytes.clone();
+    bad_dtype[28] = 42;
+    assert_invalid(&bad_dtype, "dtype");
+
+    let mut custom_dtype = bytes.clone();
+    custom_dtype[28] = 130;
+    let custom = Tensor::load_ndarray(&custom_dtype).unwrap();
+    assert_eq!(custom.dtype.to_string(), "custom[130]32");
+
+    let mut bad_size = bytes.clone();
+    bad_size[40] = 4;
+    bad_size.truncate(bytes.len() - 4);
+    assert_invalid(&bad_size, "expected 8 bytes");
+
+    let mut bad_shape = bytes.clone();
+    bad_shape[32..40].copy_from_slice(&[0xff; 8]); // -1
+    assert_invalid(&bad_shape, "invalid shape");
+
+    // the number of bytes of data would overflow to 0
+    let mut overflowing_shape = bytes[..32].to_vec();
+    put_le(&mut overflowing_shape, 1 << 62, 8);
+    put_le(&mut overflowing_shape, 0, 8); // num bytes
+    assert_invalid(&overflowing_shape, "invalid shape [4611686018427387904]");
+
+    assert_invalid(&bytes[..20], "header");
+    assert_invalid(&bytes[..bytes.len() - 1], "truncated data");
+    assert_invalid(&[&bytes[..], &[0]].concat(), "extra input");
+
+    assert!(load_param_dict(&bytes).is_err());
+  }
+
+  #[test]
+  fn test_validate() {
+    test_graph().validate().unwrap();
+    assert!(GraphExecutor::new(test_graph(), &TestModule).is_ok());
+
+    let mut graph = test_graph();
+    graph.node_row_ptr.as_mut().unwrap().pop();
+    assert_invalid_graph(graph, "node_row_ptr has 4 entries");
+
+    let mut graph = test_graph();
+    graph.node_row_ptr.as_mut().unwrap()[0] = 1;
+    assert_invalid_graph(graph, "node_row_ptr must start at 0");
+
+    let mut graph = test_graph();
+    graph.nodes[3].inputs[0].id = 3;
+    assert_invalid_graph(graph, "node 3 (`add_one`) input 0 refers to node 3");
+
+    let mut graph = test_graph();
+    graph.nodes[2].inputs[1].index = 1;
+    assert_invalid_graph(
+      graph,
+      "node 2 (`mul`) input 1 refers to nonexistent output 1",
+    );
+
+    let mut graph = test_graph();
+    graph.nodes[2]
+      .attrs
+      .as_mut()
+      .unwrap()
+      .insert("num_outputs".to_string(), "2".to_string());
+    assert_invalid_graph(graph, "node 2 (`mul`) has 2 outputs");
+
+    let mut graph = test_graph();
+    graph.nodes[3].attrs.as_mut().unwrap().remove("func_name");
+    assert_invalid_graph(graph, "node 3 (`add_one`) has invalid attrs");
+
+    let mut graph = test_graph();
+    graph.heads[0].id = 4;
+    assert_invalid_graph(graph, "head 0 refers to nonexistent output 0 of node 4");
+
+    let mut graph = test_graph();
+    graph.arg_nodes = vec![0, 2];
+    assert_invalid_graph(graph, "node 2 (`mul`) is listed in arg_nodes");
+
+    let mut graph = test_graph();
+    graph.arg_nodes = vec![0];
+    assert_invalid_graph(graph, "node 1 (`w`) is a `null` op but is not listed");
+
+    let mut graph = test_graph();
+    graph
+      .attrs
+      .as_mut()
+      .unwrap()
+      .insert("shape".to_string(), json_attr("list_shape", &[[2, 2]]));
+    assert_invalid_graph(graph, "`shape` attr has 1 entries");
+
+    let mut graph = test_graph();
+    graph.nodes[3].inputs[0].id = 5;
+    match GraphExecutor::new(graph, &TestModule) {
+      Err(Error(ErrorKind::GraphFormatError(msg), _)) => assert!(msg.contains("`add_one`")),
+      _ => panic!("GraphExecutor accepted invalid graph"),
+    }
+  }
+
+  fn json_attr<T: serde::Serialize>(type_name: &str, values: T) -> serde_json::Value {
+    serde_json::to_value((type_name, values)).unwrap()
+  }
+
+  #[test]
+  fn test_new_with_params() {
+    let x = Array::from_shape_vec((2, 2), vec![1f32, 2., 3., 4.]).unwrap();
+    // a buffer which is longer than the param, such as that of a param dict, can be bound
+    let buf = Array::from_vec(vec![2f32, 0., -1., 0.5, 9.]);
+    let mut params = HashMap::new();
+    params.insert(
+      "w".to_string(),
+      Tensor {
+        size: 4,
+        shape: vec![2, 2],
+        strides: None,
+        ..Tensor::from(&buf)
+      },
+    );
+
+    let mut exec = GraphExecutor::new_with_params(test_graph(), &TestModule, params).unwrap();
+    assert_eq!(
+      exec.get_input("w").unwrap().data.as_ptr(),
+      buf.as_ptr() as *const u8
+    );
+
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.run().unwrap();
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![3f32, 1., -2., 3.]
+    );
+
+    // setting a bound param gives the executor its own copy
+    let new_w = Array::from_vec(vec![1f32; 4]);
+    exec.set_input("w", Tensor::from(&new_w)).unwrap();
+    assert_ne!(
+      exec.get_input("w").unwrap().data.as_ptr(),
+      buf.as_ptr() as *const u8
+    );
+    exec.run().unwrap();
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 3., 4., 5.]
+    );
+    assert_eq!(buf.to_vec(), vec![2f32, 0., -1., 0.5, 9.]);
+  }
+
+
+  #[test]
+  fn test_new_with_params_invalid() {
+    let flat_w = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), Tensor::from(&flat_w));
+    let err = GraphExecutor::new_with_params(test_graph(), &TestModule, params)
+      .err()
+      .unwrap();
+    assert!(err.to_string().contains("param `w` should be"), "{}", err);
+
+    let w = Array::from_shape_vec((2, 2), vec![1f32, 2., 3., 4.]).unwrap();
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), Tensor::from(&w));
+    params.insert("unused".to_string(), Tensor::from(&w));
+    let err = GraphExecutor::new_with_params(test_graph(), &TestModule, params)
+      .err()
+      .unwrap();
+    assert!(
+      err.to_string().contains("unknown params: unused"),
+      "{}",
+      err
+    );
+  }
+
+  #[test]
+  fn test_set_input_invalid() {
+    let mut exec = GraphExecutor::new(test_graph(), &TestModule).unwrap();
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    assert!(exec.set_input("y", Tensor::from(&x)).is_err());
+    let short = Array::from_vec(vec![1f32, 2.]);
+    assert!(exec.set_input("x", Tensor::from(&short)).is_err());
+    let ints = Array::from_vec(vec![1i32, 2, 3, 4]);
+    assert!(exec.set_input("x", Tensor::from(&ints)).is_err());
+    let buf = Array::from_vec(vec![0f32; 8]);
+    let mut strided = Tensor::from(&buf);
+    strided.shape = vec![4];
+    strided.strides = Some(vec![2]);
+    assert!(exec.set_input("x", strided).is_err());
+  }
+
+  #[test]
+  fn test_new_with_params_copies_unbindable() {
+    // `w` starts 1 byte into its buffer, so it isn't aligned as float32 requires
+    let buf = Storage::new(17, Some(4)).unwrap();
+    let w = Array::from_shape_vec((2, 2), vec![2f32, 0., -1., 0.5]).unwrap();
+    let mut unaligned_w = Tensor {
+      data: Storage::from(unsafe { slice::from_raw_parts(buf.as_ptr().offset(1), 16) }),
+      ..Tensor::from(&w)
+    };
+    unaligned_w.copy(&Tensor::from(&w));
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), unaligned_w);
+
+    let mut exec = GraphExecutor::new_with_params(test_graph(), &TestModule, params).unwrap();
+    let w = exec.get_input("w").unwrap();
+    assert_ne!(w.data.as_ptr(), unsafe { buf.as_ptr().offset(1) });
+    assert_eq!(w.to_vec::<f32>(), vec![2f32, 0., -1., 0.5]);
+  }
+
+
+  #[test]
+  fn test_signature() {
+    let w = Array::from_shape_vec((2, 2), vec![1f32, 2., 3., 4.]).unwrap();
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), Tensor::from(&w));
+    let exec = GraphExecutor::new_with_params(test_graph(), &TestModule, params).unwrap();
+
+    let info = |name: &str| TensorInfo {
+      name: name.to_string(),
+      shape: vec![2, 2],
+      dtype: DataType {
+        code: DLDataTypeCode_kDLFloat as usize,
+        bits: 32,
+        lanes: 1,
+      },
+      num_bytes: 16,
+    };
+    assert_eq!(exec.inputs(), vec![info("x")]);
+    assert_eq!(exec.params(), vec![info("w")]);
+    assert_eq!(exec.outputs(), vec![info("add_one:0")]);
+
+    let mut exec = GraphExecutor::new(test_graph(), &TestModule).unwrap();
+    assert_eq!(exec.inputs().len(), 2);
+    assert!(exec.params().is_empty());
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), Tensor::from(&w));
+    params.insert("bogus".to_string(), Tensor::from(&w));
+    let report = exec.load_params(params, ParamCheck::Lenient).unwrap();
+    assert_eq!(report.unknown, vec!["bogus".to_string()]);
+    assert_eq!(exec.params(), vec![info("w")]);
+  }
+
+  #[test]
+  fn test_load_params_check() {
+    let mut exec = GraphExecutor::new(test_graph(), &TestModule).unwrap();
+    let w = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    let square_w = w.clone().into_shape((2, 2)).unwrap();
+    let short_w = Array::from_vec(vec![1f32, 2.]);
+    let params = || {
+      let mut params = HashMap::new();
+      params.insert("w".to_string(), Tensor::from(&square_w));
+      params.insert("bogus".to_string(), Tensor::from(&square_w));
+      params
+    };
+
+    let err = exec.load_params(params(), ParamCheck::Strict).unwrap_err();
+    assert!(err.to_string().contains("unknown params: bogus"));
+    assert!(exec.params().is_empty());
+
+    let report = exec.load_params(params(), ParamCheck::Lenient).unwrap();
+    assert_eq!(report.loaded, vec!["w".to_string()]);
+    assert_eq!(report.missing, vec!["x".to_string()]);
+    assert!(report.mismatched.is_empty());
+    assert_eq!(exec.params().len(), 1);
+
+    // `w` has the size of the input but not its shape, so it can be loaded only leniently
+    let mut params = HashMap::new();
+    params.insert("x".to_string(), Tensor::from(&short_w));
+    params.insert("w".to_string(), Tensor::from(&w));
+    let report = exec.load_params(params, ParamCheck::Lenient).unwrap();
+    assert_eq!(report.loaded, vec!["w".to_string()]);
+    assert_eq!(report.mismatched.len(), 2);
+    assert_eq!(report.mismatched[0].expected.num_bytes, 16);
+    assert_eq!(report.mismatched[0].actual.shape, vec![4]);
+    assert_eq!(report.mismatched[1].actual.num_bytes, 8);
+    assert!(report
+      .to_string()
+      .contains("param `x` should be float32 [2, 2] (16 bytes) but is float32 [2] (8 bytes)"));
+  }
+
+  /// Returns the shapes which change the leading dimension of the inputs of `test_graph`.
+  fn test_graph_shapes(batch: i64) -> HashMap<String, Vec<i64>> {
+    ["x", "w"]
+      .iter()
+      .map(|name| (name.to_string(), vec![batch, 2]))
+      .collect()
+  }
+
+  #[test]
+  fn test_reshape_inputs() {
+    let mut graph = test_graph();
+    // storage ids need not be contiguous
+    graph.attrs.as_mut().unwrap().insert(
+      "storage_id".to_string(),
+      json_attr("list_int", &[0, 2, 3, 5]),
+    );
+    let mut exec = GraphExecutor::new(graph, &TestModule).unwrap();
+    exec.reshape_inputs(&test_graph_shapes(3)).unwrap();
+    assert_eq!(exec.inputs()[0].shape, vec![3, 2]);
+    assert_eq!(exec.get_node_output("mul", 0).unwrap().shape, vec![3, 2]);
+    assert_eq!(exec.outputs()[0].shape, vec![3, 2]);
+
+    let x = Array::from_vec(vec![1f32, 2., 3., 4., 5., 6.]);
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.set_input("w", Tensor::from(&x)).unwrap();
+    exec.run().unwrap();
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 5., 10., 17., 26., 37.]
+    );
+
+    // op outputs which don't follow the leading dimension are given explicitly
+    let mut shapes = test_graph_shapes(1);
+    shapes.insert("add_one:0".to_string(), vec![2, 1]);
+    exec.reshape_inputs(&shapes).unwrap();
+    assert_eq!(exec.get_node_output("mul", 0).unwrap().shape, vec![1, 2]);
+    assert_eq!(exec.outputs()[0].shape, vec![2, 1]);
+  }
+
+  #[test]
+  fn test_reshape_inputs_keeps_params() {
+    let w = Array::from_shape_vec((2, 2), vec![1f32, 2., 3., 4.]).unwrap();
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), Tensor::from(&w));
+    let mut exec = GraphExecutor::new_with_params(test_graph(), &TestModule, params).unwrap();
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.run().unwrap();
+
+    let mut shapes = HashMap::new();
+    shapes.insert("y".to_string(), vec![4, 2]);
+    assert!(exec.reshape_inputs(&shapes).is_err());
+    shapes.clear();
+    shapes.insert("mul:1".to_string(), vec![4, 2]);
+    assert!(exec.reshape_inputs(&shapes).is_err());
+    shapes.clear();
+    shapes.insert("x".to_string(), vec![-4, 2]);
+    assert!(exec.reshape_inputs(&shapes).is_err());
+    shapes.clear();
+    shapes.insert("x".to_string(), vec![4, 2]);
+    shapes.insert("w".to_string(), vec![3, 2]);
+    assert!(exec.reshape_inputs(&shapes).is_err());
+    // rejected shapes leave the executor as it was
+    assert_eq!(exec.inputs()[0].shape, vec![2, 2]);
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 5., 10., 17.]
+    );
+
+    shapes.remove("w");
+    exec.reshape_inputs(&shapes).unwrap();
+    assert_eq!(exec.inputs()[0].shape, vec![4, 2]);
+    assert_eq!(exec.params()[0].shape, vec![2, 2]);
+    assert_eq!(exec.outputs()[0].shape, vec![4, 2]);
+    assert_eq!(
+      exec.get_input("w").unwrap().to_vec::<f32>(),
+      vec![1f32, 2., 3., 4.]
+    );
+  }
+
+  #[test]
+  fn test_reshape_inputs_rejected_by_op() {
+    let mut funcs = FuncModule::new();
+    funcs.register(
+      "add_one",
+      box |args: &[TVMArgValue]| {
+        let shape = unsafe {
+          let tensor = &*(args[0].value.v_handle as *const DLTensor);
+          slice::from_raw_parts(tensor.shape, tensor.ndim as usize)
+        };
+        let size = shape.iter().product::<i64>();
+        ensure!(
+          size == 4,
+          ErrorKind::FunctionCallError(format!("expected 4 elements but got {}", size))
+        );
+        TestModule.get_function("add_one").unwrap()(args)
+      },
+    );
+    let mut lib = CompositeModule::new();
+    lib.import(funcs).import(TestModule);
+    let mut exec = GraphExecutor::new(test_graph(), &lib).unwrap();
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.set_input("w", Tensor::from(&x)).unwrap();
+    exec.run().unwrap();
+
+    let err = exec.reshape_inputs(&test_graph_shapes(3)).err().unwrap();
+    assert!(err.to_string().contains("expected 4 elements but got 6"));
+    // the check neither reshapes nor overwrites the existing inputs and outputs
+    assert_eq!(exec.inputs()[0].shape, vec![2, 2]);
+    assert_eq!(exec.outputs()[0].shape, vec![2, 2]);
+    assert_eq!(
+      exec.get_input("x").unwrap().to_vec::<f32>(),
+      vec![1f32, 2., 3., 4.]
+    );
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 5., 10., 17.]
+    );
+    exec.run().unwrap();
     assert_eq!(
-      tvm_str_to_type(CompleteStr("uint111x44")).unwrap().1,
-      DataType {
-        code: DLDataTypeCode_kDLUInt as usize,
-        bits: 111,
-        lanes: 44
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 5., 10., 17.]
+    );
+  }
+
+  #[test]
+  fn test_run_concurrent() {
+    let mut builder = GraphBuilder::new();
+    let x = builder.add_input("x", &[2, 2], "float32").unwrap();
+    let w = builder.add_input("w", &[2, 2], "float32").unwrap();
+    let x1 = builder
+      .add_op("x1", "add_one", &[x], &[(&[2, 2], "float32")])
+      .unwrap();
+    let w1 = builder
+      .add_op("w1", "add_one", &[w], &[(&[2, 2], "float32")])
+      .unwrap();
+    let out = builder
+      .add_op("mul", "mul", &[x1[0], w1[0]], &[(&[2, 2], "float32")])
+      .unwrap();
+    builder.add_output(out[0]).unwrap();
+    let mut exec = GraphExecutor::new(builder.build().unwrap(), &TestModule).unwrap();
+    assert_eq!(
+      exec.op_dependencies().unwrap(),
+      vec![vec![], vec![], vec![0, 1]]
+    );
+
+    exec.set_max_concurrent_ops(2).unwrap();
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.set_input("w", Tensor::from(&x)).unwrap();
+    for _ in 0..10 {
+      exec.run().unwrap();
+      assert_eq!(
+        exec.get_output(0).unwrap().to_vec::<f32>(),
+        vec![4f32, 9., 16., 25.]
+      );
+    }
+  }
+
+  #[test]
+  fn test_op_dependencies_reuse() {
+    // `add_one` overwrites the output of `mul` in place
+    let mut graph = test_graph();
+    graph.attrs.as_mut().unwrap().insert(
+      "storage_id".to_string(),
+      serde_json::to_value(("list_int", [0, 1, 2, 0])).unwrap(),
+    );
+    let exec = GraphExecutor::new(graph, &TestModule).unwrap();
+    assert_eq!(exec.op_dependencies().unwrap(), vec![vec![], vec![0]]);
+  }
+
+  #[test]
+  fn test_partial_run() {
+    let mut exec = GraphExecutor::new(test_graph(), &TestModule).unwrap();
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.set_input("w", Tensor::from(&x)).unwrap();
+
+    exec.run_until("mul").unwrap();
+    assert_eq!(
+      exec.get_node_output("mul", 0).unwrap().to_vec::<f32>(),
+      vec![1f32, 4., 9., 16.]
+    );
+
+    let prod = Array::from_vec(vec![0f32, 1., 2., 3.]);
+    exec.set_node_output("mul", 0, Tensor::from(&prod)).unwrap();
+    exec.run_from("add_one").unwrap();
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![1f32, 2., 3., 4.]
+    );
+
+    exec.run_range(0, 4).unwrap();
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 5., 10., 17.]
+    );
+
+    assert!(exec.run_range(3, 5).is_err());
+    assert!(exec.run_until("relu").is_err());
+    assert!(exec.get_node_output("mul", 1).is_err());
+    let short = Array::from_vec(vec![0f32, 1.]);
+    let err = exec
+      .set_node_output("mul", 0, Tensor::from(&short))
+      .err()
+      .unwrap();
+    assert!(err.to_string().contains(
+      "has dtype float32 and 4 elements but value has dtype float32 and 2 elements"
+    ));
+    let ints = Array::from_vec(vec![0i32; 4]);
+    let err = exec
+      .set_node_output("mul", 0, Tensor::from(&ints))
+      .err()
+      .unwrap();
+    assert!(err.to_string().contains("but value has dtype int32 and 4 elements"));
+    let buf = Array::from_vec(vec![0f32; 8]);
+    let mut strided = Tensor::from(&buf);
+    strided.shape = vec![4];
+    strided.strides = Some(vec![2]);
+    strided.size = 4;
+    assert!(exec.set_node_output("mul", 0, strided).is_err());
+  }
+
+  #[test]
+  fn test_set_node_output_shared_param() {
+    let w = Array::from_shape_vec((2, 2), vec![1f32, 2., 3., 4.]).unwrap();
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), Tensor::from(&w));
+    let params = SharedParams::new(params).unwrap();
+    let mut exec =
+      GraphExecutor::new_with_shared_params(test_graph(), &TestModule, &params).unwrap();
+
+    let ones = Array::from_vec(vec![1f32; 4]);
+    exec.set_node_output("w", 0, Tensor::from(&ones)).unwrap();
+    exec.set_input("x", Tensor::from(&w)).unwrap();
+    exec.run().unwrap();
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 3., 4., 5.]
+    );
+    assert_eq!(
+      params.get("w").unwrap().to_vec::<f32>(),
+      vec![1f32, 2., 3., 4.]
+    );
+  }
+
+  #[test]
+  fn test_run_error() {
+    let mut funcs = FuncModule::new();
+    funcs.register("add_one", box |_args: &[TVMArgValue]| {
+      bail!(ErrorKind::FunctionCallError("invalid shape".to_string()))
+    });
+    let mut lib = CompositeModule::new();
+    lib.import(funcs).import(TestModule);
+    let mut exec = GraphExecutor::new(test_graph(), &lib).unwrap();
+    for max_concurrent_ops in 1..3 {
+      exec.set_max_concurrent_ops(max_concurrent_ops).unwrap();
+      match exec.run() {
+        Err(Error(ErrorKind::OpExecError(node_name, func_name, msg), _)) => {
+          assert_eq!(node_name, "add_one");
+          assert_eq!(func_name, "add_one");
+          assert_eq!(msg, "invalid shape");
+        }
+        _ => panic!("expected an OpExecError"),
       }
+    }
+    assert!(exec.run_until("mul").is_ok());
+    assert!(exec.run_from("add_one").is_err());
+    assert!(exec.run_debug().is_err());
+  }
+
+  #[test]
+  fn test_to_dot() {
+    let mut graph = test_graph();
+    graph.attrs.as_mut().unwrap().insert(
+      "storage_id".to_string(),
+      serde_json::to_value(("list_int", [0, 1, 2, 2])).unwrap(),
+    );
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph {"));
+    assert!(dot.contains(r#"n0 [label="x\nnull\n0: [2, 2] float32 @0", shape=ellipse];"#));
+    assert!(dot.contains(r#"n2 -> n3 [label="0"];"#));
+    assert!(dot.contains(r#"n1 -> n2 [label="0"];"#));
+    let shared_color = r#"style=filled, fillcolor="/set312/1""#;
+    assert_eq!(dot.matches(shared_color).count(), 2);
+    assert!(dot.contains(r#"add_one\n0: [2, 2] float32 @2", shape=box, "#));
+    assert!(dot.contains("peripheries=2"));
+
+    graph.attrs = None;
+    assert!(graph
+      .to_dot()
+      .contains(r#"n2 [label="mul\nmul", shape=box];"#));
+  }
+
+  #[test]
+  fn test_graph_versions() {
+    let graph_jsons = [
+      TEST_GRAPH_JSON,
+      TEST_RELAY_GRAPH_JSON,
+      TEST_LEGACY_GRAPH_JSON,
+    ];
+    for graph_json in graph_jsons.iter() {
+      let graph = Graph::try_from(*graph_json).unwrap();
+      assert_eq!(graph.node_row_ptr, Some(vec![0, 1, 2, 3, 4]));
+      assert_eq!(graph.nodes[3].inputs[0].version, 0);
+      assert_eq!(
+        graph.get_list_attr::<usize>("storage_id").unwrap(),
+        vec![0, 1, 2, 3]
+      );
+
+      let mut exec = GraphExecutor::new(graph, &TestModule).unwrap();
+      let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+      exec.set_input("x", Tensor::from(&x)).unwrap();
+      exec.set_input("w", Tensor::from(&x)).unwrap();
+      exec.run().unwrap();
+      assert_eq!(
+        exec.get_output(0).unwrap().to_vec::<f32>(),
+        vec![2f32, 5., 10., 17.]
+      );
+    }
+
+    let graph = Graph::try_from(TEST_RELAY_GRAPH_JSON).unwrap();
+    let attrs = graph.nodes[3].attrs.as_ref().unwrap();
+    assert_eq!(attrs["num_outputs"], "1");
+    assert!(!graph.nodes[2].parse_attrs().unwrap().flatten_data);
+    assert!(graph.nodes[3].parse_attrs().unwrap().flatten_data);
+
+    let legacy = Graph::try_from(TEST_LEGACY_GRAPH_JSON).unwrap();
+    let legacy_json =
+      serde_json::from_str::<serde_json::Value>(&legacy.to_json().unwrap()).unwrap();
+    assert!(legacy_json["nodes"][2].get("attrs").is_some());
+    assert_eq!(
+      legacy_json["nodes"][3]["inputs"][0],
+      serde_json::to_value([2, 0, 0]).unwrap()
+    );
+
+    assert!(Graph::try_from(TEST_GRAPH_JSON.replace("[2, 0, 0]", "[2]").as_str()).is_err());
+  }
+
+  #[test]
+  fn test_validate_devices() {
+    let mut graph = Graph::try_from(TEST_RELAY_GRAPH_JSON).unwrap();
+    graph.attrs.as_mut().unwrap().insert(
+      "device_index".to_string(),
+      serde_json::to_value(("list_int", [1, 1, 2, 1])).unwrap(),
+    );
+    assert_invalid_graph(graph, "Heterogeneous graphs");
+
+    let mut graph = Graph::try_from(TEST_RELAY_GRAPH_JSON).unwrap();
+    graph.attrs.as_mut().unwrap().insert(
+      "storage_scope".to_string(),
+      serde_json::to_value(("list_str", ["global", "", "global.texture", "global"])).unwrap(),
     );
+    assert_invalid_graph(graph, "storage scope `global.texture`");
   }
 }
@@ -0,0 +1,307 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json;
+
+use super::{
+  graph::{Entry, Graph, Node},
+  DataType, StoragePlan,
+};
+use errors::{ErrorKind, Result};
+
+/// Incrementally constructs a `Graph` of `tvm_op`s whose `node_row_ptr`, `storage_id`, `shape`
+/// and `dltype` attrs are consistent with its nodes. The `storage_id`s are planned using
+/// `StoragePlan::new`.
+///
+/// # Examples
+///
+/// ```
+/// let mut builder = GraphBuilder::new();
+/// let x = builder.add_input("x", &[2, 2], "float32").unwrap();
+/// let y = builder.add_op("add_one", "add_one", &[x], &[(&[2, 2], "float32")]).unwrap();
+/// builder.add_output(y[0]).unwrap();
+///
+/// let graph_json = builder.build().unwrap().to_json().unwrap();
+/// ```
+#[derive(Default)]
+pub struct GraphBuilder {
+  nodes: Vec<Node>,
+  arg_nodes: Vec<usize>,
+  heads: Vec<Entry>,
+  /// The shapes and dltypes of each node's outputs.
+  outputs: Vec<Vec<(Vec<i64>, String)>>,
+  node_names: HashSet<String>,
+}
+
+impl GraphBuilder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Adds a graph input (or param) with the given shape and dltype and returns its entry.
+  pub fn add_input<S: Into<String>>(
+    &mut self,
+    name: S,
+    shape: &[i64],
+    dltype: &str,
+  ) -> Result<Entry> {
+    let name = name.into();
+    self.check_name(&name)?;
+    let dtype = dltype.parse::<DataType>()?;
+    self.node_names.insert(name.clone());
+    self.arg_nodes.push(self.nodes.len());
+    self.push_node(
+      Node {
+        op: "null".to_string(),
+        name: name,
+        inputs: Vec::new(),
+        attrs: None,
+        control_deps: None,
+      },
+      vec![(shape.to_vec(), dtype.to_string())],
+    );
+    Ok(self.entry(self.nodes.len() - 1, 0))
+  }
+
+  /// Adds a `tvm_op` which calls `func_name` on `inputs` and returns entries for each of
+  /// its `outputs`, which are given as `(shape, dltype)` pairs.
+  pub fn add_op<S: Into<String>, F: Into<String>>(
+    &mut self,
+    name: S,
+    func_name: F,
+    inputs: &[Entry],
+    outputs: &[(&[i64], &str)],
+  ) -> Result<Vec<Entry>> {
+    let name = name.into();
+    self.check_name(&name)?;
+    for input in inputs.iter() {
+      self.check_entry(input)?;
+    }
+    let dtypes = outputs
+      .iter()
+      .map(|&(_, dltype)| dltype.parse::<DataType>())
+      .collect::<Result<Vec<DataType>>>()?;
+    self.node_names.insert(name.clone());
+
+    let mut attrs = HashMap::new();
+    attrs.insert("func_name".to_string(), func_name.into());
+    attrs.insert("num_inputs".to_string(), inputs.len().to_string());
+    attrs.insert("num_outputs".to_string(), outputs.len().to_string());
+    attrs.insert("flatten_data".to_string(), "0".to_string());
+    self.push_node(
+      Node {
+        op: "tvm_op".to_string(),
+        name: name,
+        inputs: inputs.to_vec(),
+        attrs: Some(attrs),
+        control_deps: None,
+      },
+      outputs
+        .iter()
+        .zip(dtypes)
+        .map(|(&(shape, _), dtype)| (shape.to_vec(), dtype.to_string()))
+        .collect(),
+    );
+    let id = self.nodes.len() - 1;
+    Ok(
+      (0..outputs.len())
+        .map(|index| self.entry(id, index))
+        .collect(),
+    )
+  }
+
+  /// Marks `entry` as an output of the graph.
+  pub fn add_output(&mut self, entry: Entry) -> Result<()> {
+    self.check_entry(&entry)?;
+    self.heads.push(entry);
+    Ok(())
+  }
+
+  /// Returns the constructed `Graph`.
+  pub fn build(self) -> Result<Graph> {
+    let mut node_row_ptr = vec![0];
+    for outputs in self.outputs.iter() {
+      let row = node_row_ptr[node_row_ptr.len() - 1] + outputs.len();
+      node_row_ptr.push(row);
+    }
+    let (shapes, dltypes): (Vec<Vec<i64>>, Vec<String>) =
+      self.outputs.into_iter().flat_map(|outputs| outputs).unzip();
+
+    let mut attrs = HashMap::new();
+    attrs.insert(
+      "shape".to_string(),
+      serde_json::to_value(("list_shape", shapes))?,
+    );
+    attrs.insert(
+      "dltype".to_string(),
+      serde_json::to_value(("list_str", dltypes))?,
+    );
+
+    let mut graph = Graph {
+      nodes: self.nodes,
+      arg_nodes: self.arg_nodes,
+      heads: self.heads,
+      node_row_ptr: Some(node_row_ptr),
+      attrs: Some(attrs),
+    };
+    let plan = StoragePlan::new(&graph)?;
+    graph.attrs.as_mut().unwrap().insert(
+      "storage_id".to_string(),
+      serde_json::to_value(("list_int", plan.storage_ids))?,
+    );
+    Ok(graph)
+  }
+
+  fn push_node(&mut self, node: Node, outputs: Vec<(Vec<i64>, String)>) {
+    self.nodes.push(node);
+    self.outputs.push(outputs);
+  }
+
+  fn entry(&self, id: usize, index: usize) -> Entry {
+    Entry {
+      id: id,
+      index: index,
+      version: 0,
+    }
+  }
+
+  /// Ensures that no node is already named `name`.
+  fn check_name(&self, name: &str) -> Result<()> {
+    ensure!(
+      !self.node_names.contains(name),
+      ErrorKind::GraphFormatError(format!("Duplicate node name `{}`", name))
+    );
+    Ok(())
+  }
+
+  /// Ensures that `entry` refers to an existing node output.
+  fn check_entry(&self, entry: &Entry) -> Result<()> {
+    ensure!(
+      self
+        .outputs
+        .get(entry.id)
+        .map_or(false, |outputs| entry.index < outputs.len()),
+      ErrorKind::GraphFormatError(format!(
+        "Entry refers to nonexistent output {} of node {}",
+        entry.index, entry.id
+      ))
+    );
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::convert::TryFrom;
+
+  use ndarray::Array;
+
+  use super::*;
+  use runtime::{
+    graph::tests::{TestModule, TEST_GRAPH_JSON},
+    GraphExecutor, Tensor,
+  };
+
+  fn build_test_graph() -> Graph {
+    let mut builder = GraphBuilder::new();
+    let x = builder.add_input("x", &[2, 2], "float32").unwrap();
+    let w = builder.add_input("w", &[2, 2], "float32").unwrap();
+    let prod = builder
+      .add_op("mul", "mul", &[x, w], &[(&[2, 2], "float32")])
+      .unwrap();
+    let out = builder
+      .add_op(
+        "add_one".to_string(),
+        "add_one",
+        &prod,
+        &[(&[2, 2], "float32")],
+      )
+      .unwrap();
+    builder.add_output(out[0]).unwrap();
+    builder.build().unwrap()
+  }
+
+  #[test]
+  fn test_build() {
+    let graph = build_test_graph();
+    let expected = Graph::try_from(TEST_GRAPH_JSON).unwrap();
+    assert_eq!(graph.arg_nodes, expected.arg_nodes);
+    assert_eq!(graph.heads, expected.heads);
+    assert_eq!(graph.node_row_ptr, expected.node_row_ptr);
+    for attr in ["storage_id", "shape", "dltype"].iter() {
+      assert_eq!(
+        graph.attrs.as_ref().unwrap()[*attr],
+        expected.attrs.as_ref().unwrap()[*attr]
+      );
+    }
+    for (node, expected_node) in graph.nodes.iter().zip(expected.nodes.iter()) {
+      assert_eq!(node.op, expected_node.op);
+      assert_eq!(node.inputs, expected_node.inputs);
+      if node.op == "tvm_op" {
+        let attrs = node.attrs.as_ref().unwrap();
+        assert_eq!(
+          attrs["func_name"],
+          expected_node.attrs.as_ref().unwrap()["func_name"]
+        );
+      }
+    }
+
+    let graph_json = serde_json::from_str::<serde_json::Value>(&graph.to_json().unwrap()).unwrap();
+    assert_eq!(graph_json["nodes"][2]["inputs"][1], json_array(&[1, 0, 0]));
+    assert!(graph_json["nodes"][0].get("attrs").is_none());
+  }
+
+  #[test]
+  fn test_build_and_run() {
+    let graph = Graph::try_from(build_test_graph().to_json().unwrap().as_str()).unwrap();
+    let mut exec = GraphExecutor::new(graph, &TestModule).unwrap();
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.set_input("w", Tensor::from(&x)).unwrap();
+    exec.run().unwrap();
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 5., 10., 17.]
+    );
+  }
+
+  #[test]
+  fn test_build_reuses_storage() {
+    let mut builder = GraphBuilder::new();
+    let x = builder.add_input("x", &[2], "float32").unwrap();
+    let a = builder.add_op("a", "add_one", &[x], &[(&[2], "float32")]).unwrap();
+    let b = builder.add_op("b", "add_one", &a, &[(&[2], "float32")]).unwrap();
+    let c = builder.add_op("c", "add_one", &b, &[(&[2], "float32")]).unwrap();
+    builder.add_output(c[0]).unwrap();
+    let graph = builder.build().unwrap();
+    assert_eq!(
+      graph.get_list_attr::<usize>("storage_id").unwrap(),
+      vec![0, 1, 2, 1]
+    );
+  }
+
+  #[test]
+  fn test_invalid() {
+    let mut builder = GraphBuilder::new();
+    let x = builder.add_input("x", &[2], "float32").unwrap();
+    assert!(builder.add_input("x", &[2], "float32").is_err());
+    assert!(builder.add_input("y", &[2], "floaty32").is_err());
+    let bad_dltype = builder.add_op("f", "f", &[x], &[(&[2], "float32x")]);
+    assert!(bad_dltype.is_err());
+    let bad_entry = Entry {
+      id: 0,
+      index: 1,
+      version: 0,
+    };
+    let bad_input = builder.add_op("f", "f", &[bad_entry], &[(&[2], "float32")]);
+    assert!(bad_input.is_err());
+    assert!(builder.add_output(bad_entry).is_err());
+    builder.add_op("f", "f", &[x], &[(&[2], "float32")]).unwrap();
+    assert!(builder.add_op("f", "f", &[x], &[(&[2], "float32")]).is_err());
+    assert!(builder.add_op("x", "f", &[x], &[(&[2], "float32")]).is_err());
+    assert!(builder.add_input("f", &[2], "float32").is_err());
+  }
+
+  fn json_array(values: &[usize]) -> serde_json::Value {
+    serde_json::to_value(values).unwrap()
+  }
+}
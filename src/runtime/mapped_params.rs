@@ -0,0 +1,119 @@
+use std::{collections::HashMap, fs::File, path::Path};
+
+use memmap::Mmap;
+
+use super::{load_param_dict, Graph, GraphExecutor, Module, Tensor};
+use errors::Result;
+
+/// A param dict file mapped read-only into memory so that a `GraphExecutor` can bind to the
+/// params without reading or copying them.
+///
+/// # Examples
+///
+/// ```
+/// let params = MappedParams::open("deploy_param.params").unwrap();
+/// let mut exec = GraphExecutor::new_with_mapped_params(graph, &SystemLibModule, &params).unwrap();
+/// ```
+pub struct MappedParams {
+  mmap: Mmap,
+}
+
+impl MappedParams {
+  /// Maps the param dict, saved using `nnvm.compiler.save_param_dict`, at `path`.
+  /// The file must not be modified while it is mapped.
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+    let file = File::open(path)?;
+    Ok(MappedParams {
+      mmap: unsafe { Mmap::map(&file)? },
+    })
+  }
+
+  /// Returns the params, which borrow their data from the mapping.
+  pub fn params(&self) -> Result<HashMap<String, Tensor>> {
+    load_param_dict(&self.mmap)
+  }
+
+  pub fn as_bytes(&self) -> &[u8] {
+    &self.mmap
+  }
+}
+
+impl<'m, 't> GraphExecutor<'m, 't> {
+  /// Creates a `GraphExecutor` which binds to the mapped `params` as in `new_with_params`.
+  pub fn new_with_mapped_params<M: 'm + Module>(
+    graph: Graph,
+    lib: &'m M,
+    params: &'t MappedParams,
+  ) -> Result<Self> {
+    Self::new_with_params(graph, lib, params.params()?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{env, fs, process};
+
+  use ndarray::Array;
+
+  use super::*;
+  use runtime::{graph::tests::TestModule, save_param_dict, GraphBuilder};
+
+  /// Returns a graph which computes `x * conv0_weight + 1` as does `TEST_GRAPH_JSON`.
+  fn test_graph() -> Graph {
+    let mut builder = GraphBuilder::new();
+    let x = builder.add_input("x", &[4], "float32").unwrap();
+    let w = builder.add_input("conv0_weight", &[4], "float32").unwrap();
+    let prod = builder
+      .add_op("mul", "mul", &[x, w], &[(&[4], "float32")])
+      .unwrap();
+    let out = builder
+      .add_op("add_one", "add_one", &prod, &[(&[4], "float32")])
+      .unwrap();
+    builder.add_output(out[0]).unwrap();
+    builder.build().unwrap()
+  }
+
+  #[test]
+  fn test_mapped_params() {
+    let w = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    let new_w = w.map(|v| v * 2.);
+    // the data of `conv0_weight` is at offset 100, which is aligned as float32 requires
+    let mut params = HashMap::new();
+    params.insert("conv0_weight".to_string(), Tensor::from(&w));
+    let param_bytes = save_param_dict(&params).unwrap();
+    let path = env::temp_dir().join(format!("test_mapped_params_{}.params", process::id()));
+    fs::write(&path, &param_bytes).unwrap();
+
+    let params = MappedParams::open(&path).unwrap();
+    assert_eq!(params.as_bytes(), &param_bytes[..]);
+    let mut exec =
+      GraphExecutor::new_with_mapped_params(test_graph(), &TestModule, &params).unwrap();
+    let offset = exec.get_input("conv0_weight").unwrap().data.as_ptr() as usize
+      - params.as_bytes().as_ptr() as usize;
+    assert_eq!(offset, 100);
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.run().unwrap();
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 5., 10., 17.]
+    );
+
+    exec.set_input("conv0_weight", Tensor::from(&new_w)).unwrap();
+    exec.run().unwrap();
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![3f32, 9., 19., 33.]
+    );
+    assert_eq!(params.as_bytes(), &param_bytes[..]);
+
+    drop(exec);
+    drop(params);
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn test_mapped_params_missing() {
+    assert!(MappedParams::open("/nonexistent/graph.params").is_err());
+  }
+}
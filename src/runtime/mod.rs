@@ -1,25 +1,77 @@
 mod allocator;
 mod array;
+mod bundle;
+mod debug;
+#[cfg(unix)]
+mod dso_module;
 mod module;
+mod op_scheduler;
 #[macro_use]
 mod packed_func;
 mod graph;
+mod graph_builder;
+#[cfg(not(any(target_arch = "wasm32", target_env = "sgx")))]
+mod mapped_params;
+mod param_reader;
+#[cfg(not(any(target_arch = "wasm32", target_env = "sgx")))]
+mod profiler;
+mod registry;
 #[cfg(target_env = "sgx")]
 #[macro_use]
 pub mod sgx;
+mod shared_params;
+mod storage_plan;
 mod threading;
+mod typed_packed_func;
 mod workspace;
 
-use std::os::raw::c_char;
+use std::{
+  cell::RefCell,
+  ffi::{CStr, CString},
+  os::raw::c_char,
+};
 
-pub use self::{array::*, graph::*, module::*, packed_func::*, threading::*, workspace::*};
+#[cfg(unix)]
+pub use self::dso_module::*;
+pub use self::{
+  array::*, bundle::*, debug::*, graph::*, graph_builder::*, module::*, packed_func::*,
+  param_reader::*, registry::*, shared_params::*, storage_plan::*, threading::*,
+  typed_packed_func::*, workspace::*,
+};
+#[cfg(not(any(target_arch = "wasm32", target_env = "sgx")))]
+pub use self::{mapped_params::*, profiler::*};
 
+thread_local! {
+  /// The message of the last error on this thread, as returned by `TVMGetLastError`.
+  static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// Sets the error returned by `TVMGetLastError` on this thread.
+pub(super) fn set_last_error<S: Into<Vec<u8>>>(msg: S) {
+  let msg = CString::new(msg).unwrap_or_else(|err| {
+    let nul_pos = err.nul_position();
+    CString::new(&err.into_vec()[..nul_pos]).unwrap()
+  });
+  LAST_ERROR.with(|last_error| *last_error.borrow_mut() = msg);
+}
+
+/// Returns the error most recently set on this thread.
+pub(super) fn last_error() -> String {
+  LAST_ERROR.with(|last_error| last_error.borrow().to_string_lossy().into_owned())
+}
+
+/// Sets the error returned by `TVMGetLastError` on this thread. TVM functions call this
+/// before returning a nonzero status.
 #[no_mangle]
 pub extern "C" fn TVMAPISetLastError(cmsg: *const c_char) {
-  #[cfg(not(target_env = "sgx"))]
-  unsafe {
-    panic!(std::ffi::CStr::from_ptr(cmsg).to_str().unwrap());
-  }
+  set_last_error(unsafe { CStr::from_ptr(cmsg).to_bytes() });
   #[cfg(target_env = "sgx")]
   ocall_packed!("__sgx_set_last_error__", cmsg);
 }
+
+/// Returns the message of the last error on this thread. The message remains valid until the
+/// next error is set on the same thread.
+#[no_mangle]
+pub extern "C" fn TVMGetLastError() -> *const c_char {
+  LAST_ERROR.with(|last_error| last_error.borrow().as_ptr())
+}
@@ -1,14 +1,47 @@
 use std::{
-  collections::HashMap, convert::AsRef, ffi::CStr, os::raw::c_char, string::String, sync::Mutex,
+  collections::HashMap,
+  convert::AsRef,
+  ffi::CStr,
+  os::raw::c_char,
+  string::String,
+  sync::{Arc, Mutex},
 };
 
 use ffi::runtime::BackendPackedCFunc;
-use runtime::packed_func::{wrap_backend_packed_func, PackedFunc};
+use runtime::packed_func::{wrap_backend_packed_func, PackedFunc, TVMArgValue};
 
 pub trait Module {
   fn get_function<S: AsRef<str>>(&self, name: S) -> Option<PackedFunc>;
 }
 
+impl<'a, M: Module> Module for &'a M {
+  fn get_function<S: AsRef<str>>(&self, name: S) -> Option<PackedFunc> {
+    (**self).get_function(name)
+  }
+}
+
+/// An object-safe `Module` so that modules of different types can be imported together.
+pub(super) trait ModuleObject {
+  fn get_function_by_name(&self, name: &str) -> Option<PackedFunc>;
+}
+
+impl<M: Module> ModuleObject for M {
+  fn get_function_by_name(&self, name: &str) -> Option<PackedFunc> {
+    self.get_function(name)
+  }
+}
+
+/// Returns the first function named `name` in `modules`.
+pub(super) fn find_function(
+  modules: &[Box<ModuleObject + Send + Sync>],
+  name: &str,
+) -> Option<PackedFunc> {
+  modules
+    .iter()
+    .filter_map(|module| module.get_function_by_name(name))
+    .next()
+}
+
 pub struct SystemLibModule;
 
 lazy_static! {
@@ -32,6 +65,78 @@ impl Default for SystemLibModule {
   }
 }
 
+/// A `Module` which resolves each function from the first of its imported modules which has a
+/// function of that name, so that a graph can use functions from several modules.
+///
+/// # Examples
+///
+/// ```
+/// let mut funcs = FuncModule::new();
+/// funcs.register("my_op", box |args: &[TVMArgValue]| Ok(TVMRetValue::default()));
+///
+/// let mut lib = CompositeModule::new();
+/// lib
+///   .import(SystemLibModule::default())
+///   .import(DsoModule::new("deploy_lib.so").unwrap())
+///   .import(funcs);
+/// let mut exec = GraphExecutor::new(graph, &lib).unwrap();
+/// ```
+#[derive(Default)]
+pub struct CompositeModule {
+  imports: Vec<Box<ModuleObject + Send + Sync>>,
+}
+
+impl CompositeModule {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Appends `module` to the imported modules as does TVM's `TVMModImport`.
+  /// Modules which were imported earlier take precedence.
+  pub fn import<M: 'static + Module + Send + Sync>(&mut self, module: M) -> &mut Self {
+    self.imports.push(box module);
+    self
+  }
+
+  pub fn len(&self) -> usize {
+    self.imports.len()
+  }
+}
+
+impl Module for CompositeModule {
+  fn get_function<S: AsRef<str>>(&self, name: S) -> Option<PackedFunc> {
+    find_function(&self.imports, name.as_ref())
+  }
+}
+
+/// A `Module` of functions implemented in Rust.
+#[derive(Default)]
+pub struct FuncModule {
+  funcs: HashMap<String, Arc<PackedFunc>>,
+}
+
+impl FuncModule {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Adds `func` as the function named `name`, replacing any function of the same name.
+  pub fn register<S: Into<String>>(&mut self, name: S, func: PackedFunc) -> &mut Self {
+    self.funcs.insert(name.into(), Arc::new(func));
+    self
+  }
+}
+
+impl Module for FuncModule {
+  fn get_function<S: AsRef<str>>(&self, name: S) -> Option<PackedFunc> {
+    self.funcs.get(name.as_ref()).map(|func| {
+      let func = func.clone();
+      let wrapped: PackedFunc = box move |args: &[TVMArgValue]| func(args);
+      wrapped
+    })
+  }
+}
+
 #[no_mangle]
 pub extern "C" fn TVMBackendRegisterSystemLibSymbol(
   cname: *const c_char,
@@ -44,3 +149,36 @@ pub extern "C" fn TVMBackendRegisterSystemLibSymbol(
     .insert(name.to_string(), func);
   return 0;
 }
+
+#[cfg(test)]
+mod tests {
+  use std::convert::TryFrom;
+
+  use super::*;
+  use runtime::TVMRetValue;
+
+  fn const_func(val: i64) -> PackedFunc {
+    box move |_args: &[TVMArgValue]| Ok(TVMRetValue::from(val))
+  }
+
+  #[test]
+  fn test_composite_module() {
+    let mut first = FuncModule::new();
+    first
+      .register("a", const_func(1))
+      .register("b", const_func(2));
+    let mut second = FuncModule::new();
+    second
+      .register("b", const_func(3))
+      .register("c", const_func(4));
+
+    let mut lib = CompositeModule::new();
+    lib.import(first).import(second);
+    assert_eq!(lib.len(), 2);
+    let call = |name: &str| i64::try_from(lib.get_function(name).unwrap()(&[]).unwrap()).unwrap();
+    assert_eq!(call("a"), 1);
+    assert_eq!(call("b"), 2);
+    assert_eq!(call("c"), 4);
+    assert!(lib.get_function("d").is_none());
+  }
+}
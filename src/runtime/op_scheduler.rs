@@ -0,0 +1,235 @@
+use std::{
+  mem,
+  panic::{self, AssertUnwindSafe},
+  sync::{
+    mpsc::{self, Sender},
+    Condvar, Mutex,
+  },
+  thread::{self, JoinHandle},
+};
+
+use errors::{Error, Result};
+
+/// Runs the ops of a graph concurrently on a pool of worker threads such that each op starts
+/// only after the ops on which it depends have completed.
+///
+/// At most `max_concurrent_ops` ops run at once: one on the calling thread and the rest on
+/// workers. Each worker has its own intra-op thread pool for `TVMBackendParallelLaunch`.
+pub(super) struct OpScheduler {
+  /// The number of ops on which each op depends.
+  num_deps: Vec<usize>,
+  /// The ops which depend on each op.
+  dependents: Vec<Vec<usize>>,
+  workers: Vec<Worker>,
+}
+
+struct Worker {
+  jobs: Option<Sender<Job>>,
+  handle: Option<JoinHandle<()>>,
+}
+
+/// A request for a worker to help run the ops of the graph.
+struct Job {
+  /// Borrowed from `OpScheduler::run`, which waits for the worker to finish with it.
+  participate: *const (Fn() + Sync),
+  done: Sender<thread::Result<()>>,
+}
+
+unsafe impl Send for Job {}
+
+struct RunState {
+  ready: Vec<usize>,
+  num_pending_deps: Vec<usize>,
+  num_remaining: usize,
+  /// Set when an op fails or panics so that no further ops are started.
+  aborted: bool,
+  /// The error of the first op which failed.
+  error: Option<Error>,
+}
+
+impl OpScheduler {
+  /// Creates an `OpScheduler` for ops where op `i` must run after each op in `deps[i]`.
+  pub(super) fn new(deps: Vec<Vec<usize>>, max_concurrent_ops: usize) -> Self {
+    let mut dependents = vec![Vec::new(); deps.len()];
+    for (op, op_deps) in deps.iter().enumerate() {
+      for &dep in op_deps.iter() {
+        dependents[dep].push(op);
+      }
+    }
+    OpScheduler {
+      num_deps: deps.iter().map(|op_deps| op_deps.len()).collect(),
+      dependents: dependents,
+      workers: spawn_workers(max_concurrent_ops.saturating_sub(1)),
+    }
+  }
+
+  /// Calls `run_op` with the index of each op and returns once all ops have run.
+  /// If an op fails or panics, no further ops are started and the first error or the panic
+  /// is propagated.
+  pub(super) fn run<F: Fn(usize) -> Result<()> + Sync>(&self, run_op: F) -> Result<()> {
+    let state = Mutex::new(RunState {
+      ready: (0..self.num_deps.len())
+        .rev()
+        .filter(|&op| self.num_deps[op] == 0)
+        .collect(),
+      num_pending_deps: self.num_deps.clone(),
+      num_remaining: self.num_deps.len(),
+      aborted: false,
+      error: None,
+    });
+    let cvar = Condvar::new();
+
+    let participate = || loop {
+      let op = {
+        let mut state = state.lock().unwrap();
+        while state.ready.is_empty() && state.num_remaining > 0 && !state.aborted {
+          state = cvar.wait(state).unwrap();
+        }
+        match state.ready.pop() {
+          Some(op) if !state.aborted => op,
+          _ => return,
+        }
+      };
+      let result = panic::catch_unwind(AssertUnwindSafe(|| run_op(op)));
+      let mut state = state.lock().unwrap();
+      state.num_remaining -= 1;
+      for &dependent in self.dependents[op].iter() {
+        state.num_pending_deps[dependent] -= 1;
+        if state.num_pending_deps[dependent] == 0 {
+          state.ready.push(dependent);
+        }
+      }
+      state.aborted |= result.as_ref().map_or(true, |result| result.is_err());
+      cvar.notify_all();
+      match result {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => {
+          state.error.get_or_insert(err);
+        }
+        Err(err) => {
+          drop(state);
+          panic::resume_unwind(err);
+        }
+      }
+    };
+
+    let (done_tx, done_rx) = mpsc::channel();
+    let participate: &(Fn() + Sync) = &participate;
+    for worker in self.workers.iter() {
+      worker
+        .jobs
+        .as_ref()
+        .unwrap()
+        .send(Job {
+          participate: unsafe { mem::transmute(participate) },
+          done: done_tx.clone(),
+        }).unwrap();
+    }
+    let result = panic::catch_unwind(AssertUnwindSafe(participate));
+    // the workers borrow `participate`, so they must finish before it is dropped
+    let worker_results = (0..self.workers.len())
+      .map(|_| done_rx.recv().unwrap())
+      .collect::<Vec<thread::Result<()>>>();
+    for result in Some(result).into_iter().chain(worker_results) {
+      if let Err(err) = result {
+        panic::resume_unwind(err);
+      }
+    }
+    let error = state.lock().unwrap().error.take();
+    match error {
+      Some(err) => Err(err),
+      None => Ok(()),
+    }
+  }
+}
+
+impl Drop for OpScheduler {
+  fn drop(&mut self) {
+    for worker in self.workers.iter_mut() {
+      // closing the channel stops the worker
+      worker.jobs.take();
+      if let Some(handle) = worker.handle.take() {
+        handle.join().ok();
+      }
+    }
+  }
+}
+
+#[cfg(not(any(target_arch = "wasm32", target_env = "sgx")))]
+fn spawn_workers(num_workers: usize) -> Vec<Worker> {
+  (0..num_workers)
+    .map(|_| {
+      let (jobs_tx, jobs_rx) = mpsc::channel::<Job>();
+      let handle = thread::spawn(move || {
+        for job in jobs_rx.iter() {
+          let result = panic::catch_unwind(AssertUnwindSafe(|| unsafe { (*job.participate)() }));
+          job.done.send(result).unwrap();
+        }
+      });
+      Worker {
+        jobs: Some(jobs_tx),
+        handle: Some(handle),
+      }
+    }).collect()
+}
+
+#[cfg(any(target_arch = "wasm32", target_env = "sgx"))]
+fn spawn_workers(_num_workers: usize) -> Vec<Worker> {
+  Vec::new() // ops run only on the calling thread
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+  use super::*;
+
+  #[test]
+  fn test_run_order() {
+    // 0 -> {1, 2} -> 3
+    let scheduler = OpScheduler::new(vec![vec![], vec![0], vec![0], vec![1, 2]], 3);
+    let counter = AtomicUsize::new(0);
+    let order = (0..4).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+    for _ in 0..20 {
+      scheduler
+        .run(|op| {
+          order[op].store(counter.fetch_add(1, Ordering::SeqCst), Ordering::SeqCst);
+          Ok(())
+        }).unwrap();
+      let order = order
+        .iter()
+        .map(|o| o.load(Ordering::SeqCst) % 4)
+        .collect::<Vec<usize>>();
+      assert_eq!(order[0], 0);
+      assert_eq!(order[3], 3);
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "op failed")]
+  fn test_run_panic() {
+    let scheduler = OpScheduler::new(vec![vec![], vec![], vec![0, 1]], 2);
+    scheduler
+      .run(|op| {
+        if op == 1 {
+          panic!("op failed");
+        }
+        Ok(())
+      }).unwrap();
+  }
+
+  #[test]
+  fn test_run_error() {
+    let scheduler = OpScheduler::new(vec![vec![], vec![], vec![0, 1]], 2);
+    let ran_last = AtomicBool::new(false);
+    let err = scheduler
+      .run(|op| match op {
+        1 => bail!("op failed"),
+        2 => Ok(ran_last.store(true, Ordering::SeqCst)),
+        _ => Ok(()),
+      }).err()
+      .unwrap();
+    assert_eq!(err.to_string(), "op failed");
+    assert!(!ran_last.load(Ordering::SeqCst));
+  }
+}
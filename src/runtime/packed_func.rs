@@ -1,15 +1,27 @@
-use std::{any::Any, convert::TryFrom, marker::PhantomData, os::raw::c_void};
+use std::{
+  any::Any,
+  cell::RefCell,
+  convert::TryFrom,
+  ffi::{CStr, CString},
+  marker::PhantomData,
+  os::raw::{c_int, c_void},
+  sync::Arc,
+};
 
+use super::{last_error, set_last_error};
 use ffi::runtime::{
-  BackendPackedCFunc, DLDataTypeCode_kDLFloat, DLDataTypeCode_kDLInt, DLTensor,
-  TVMTypeCode_kArrayHandle, TVMTypeCode_kHandle, TVMValue,
+  BackendPackedCFunc, DLDataTypeCode_kDLFloat, DLDataTypeCode_kDLInt, DLDataTypeCode_kDLUInt,
+  DLTensor, TVMFunctionHandle, TVMPackedCFunc, TVMPackedCFuncFinalizer, TVMRetValueHandle,
+  TVMTypeCode_kArrayHandle, TVMTypeCode_kHandle, TVMTypeCode_kNull, TVMTypeCode_kStr, TVMValue,
 };
 
 use errors::*;
 
-pub type PackedFunc = Box<Fn(&[TVMArgValue]) -> TVMRetValue + Send + Sync>;
+/// A function which takes type-erased arguments. It fails with
+/// `ErrorKind::FunctionCallError` if, for instance, a TVM function reports an error.
+pub type PackedFunc = Box<Fn(&[TVMArgValue]) -> Result<TVMRetValue> + Send + Sync>;
 
-/// Calls a packed function and returns a `TVMRetValue`.
+/// Calls a packed function and returns a `Result<TVMRetValue>`.
 ///
 /// # Example
 ///
@@ -77,6 +89,81 @@ impl_prim_tvm_arg!(i64, v_int64);
 impl_prim_tvm_arg!(u64, v_int64);
 impl_prim_tvm_arg!(bool, v_int64);
 
+/// Creates a conversion from a `TVMArgValue` to a primitive type, if the type codes match
+/// and, for an integer type, the value is in its range.
+macro_rules! impl_prim_try_from_tvm_arg {
+  ($type:ty,v_int64) => {
+    impl<'a> TryFrom<TVMArgValue<'a>> for $type {
+      type Error = Error;
+      fn try_from(arg: TVMArgValue<'a>) -> Result<$type> {
+        ensure!(
+          arg.type_code == DLDataTypeCode_kDLInt as i64
+            || arg.type_code == DLDataTypeCode_kDLUInt as i64,
+          ErrorKind::TryFromTVMArgValueError(stringify!($type).to_string(), arg.type_code)
+        );
+        let val = unsafe { arg.value.v_int64 };
+        Ok(<$type>::try_from(val).map_err(|_| {
+          ErrorKind::TryFromTVMArgValueError(
+            format!("{} (the value {} is out of range)", stringify!($type), val),
+            arg.type_code,
+          )
+        })?)
+      }
+    }
+  };
+  ($type:ty,v_float64) => {
+    impl<'a> TryFrom<TVMArgValue<'a>> for $type {
+      type Error = Error;
+      fn try_from(arg: TVMArgValue<'a>) -> Result<$type> {
+        ensure!(
+          arg.type_code == DLDataTypeCode_kDLFloat as i64,
+          ErrorKind::TryFromTVMArgValueError(stringify!($type).to_string(), arg.type_code)
+        );
+        Ok(unsafe { arg.value.v_float64 } as $type)
+      }
+    }
+  };
+}
+
+impl_prim_try_from_tvm_arg!(f32, v_float64);
+impl_prim_try_from_tvm_arg!(f64, v_float64);
+impl_prim_try_from_tvm_arg!(i8, v_int64);
+impl_prim_try_from_tvm_arg!(u8, v_int64);
+impl_prim_try_from_tvm_arg!(i32, v_int64);
+impl_prim_try_from_tvm_arg!(u32, v_int64);
+impl_prim_try_from_tvm_arg!(i64, v_int64);
+impl_prim_try_from_tvm_arg!(u64, v_int64);
+
+impl<'a> TryFrom<TVMArgValue<'a>> for bool {
+  type Error = Error;
+  fn try_from(arg: TVMArgValue<'a>) -> Result<bool> {
+    Ok(
+      i64::try_from(arg)
+        .map_err(|_| ErrorKind::TryFromTVMArgValueError("bool".to_string(), arg.type_code))?
+        != 0,
+    )
+  }
+}
+
+impl<'a> TryFrom<TVMArgValue<'a>> for String {
+  type Error = Error;
+  fn try_from(arg: TVMArgValue<'a>) -> Result<String> {
+    ensure!(
+      arg.type_code == TVMTypeCode_kStr as i64,
+      ErrorKind::TryFromTVMArgValueError("String".to_string(), arg.type_code)
+    );
+    ensure!(
+      !unsafe { arg.value.v_str }.is_null(),
+      "String argument is null"
+    );
+    Ok(
+      unsafe { CStr::from_ptr(arg.value.v_str) }
+        .to_string_lossy()
+        .into_owned(),
+    )
+  }
+}
+
 /// Creates a conversion to a `TVMArgValue` for an object handle.
 impl<'a, T> From<*const T> for TVMArgValue<'a> {
   fn from(ptr: *const T) -> Self {
@@ -265,10 +352,30 @@ impl_prim_ret_value!(isize, 0);
 impl_prim_ret_value!(usize, 1);
 impl_boxed_ret_value!(String, 11);
 
+impl From<()> for TVMRetValue {
+  fn from(_val: ()) -> Self {
+    TVMRetValue {
+      prim_value: 0,
+      box_value: box (),
+      type_code: TVMTypeCode_kNull as i64,
+    }
+  }
+}
+
+/// Discards the return value, as for functions which return nothing.
+impl TryFrom<TVMRetValue> for () {
+  type Error = Error;
+  fn try_from(_ret: TVMRetValue) -> Result<()> {
+    Ok(())
+  }
+}
+
 // @see `WrapPackedFunc` in `llvm_module.cc`.
 pub(super) fn wrap_backend_packed_func(func: BackendPackedCFunc) -> PackedFunc {
   box move |args: &[TVMArgValue]| {
-    func(
+    // so that a message left over from an earlier error is not reported if `func` sets none
+    set_last_error("");
+    let status = func(
       args
         .iter()
         .map(|ref arg| arg.value)
@@ -281,6 +388,445 @@ pub(super) fn wrap_backend_packed_func(func: BackendPackedCFunc) -> PackedFunc {
         .as_ptr() as *const i32,
       args.len() as i32,
     );
-    TVMRetValue::default()
+    if status != 0 {
+      let msg = match last_error() {
+        ref msg if msg.is_empty() => format!("function failed with status {}", status),
+        msg => msg,
+      };
+      bail!(ErrorKind::FunctionCallError(msg));
+    }
+    Ok(TVMRetValue::default())
+  }
+}
+
+/// Returns the message of an error returned by a `PackedFunc` without the description of
+/// `ErrorKind::FunctionCallError`, so that messages are not prefixed again as they propagate.
+pub(super) fn call_error_message(err: Error) -> String {
+  match err {
+    Error(ErrorKind::FunctionCallError(msg), _) => msg,
+    err => err.to_string(),
+  }
+}
+
+thread_local! {
+  /// The string most recently returned through `TVMFuncCall` on this thread.
+  static RET_STR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// Converts the return value of a function called through `TVMFuncCall` into a `TVMValue` and
+/// type code. Integers, floats, strings, and null can be returned.
+fn ret_tvm_value(ret: TVMRetValue) -> Result<(TVMValue, c_int)> {
+  Ok(match ret.type_code {
+    0 | 1 => (
+      TVMValue {
+        v_int64: ret.prim_value as i64,
+      },
+      DLDataTypeCode_kDLInt as c_int,
+    ),
+    2 => (
+      TVMValue {
+        v_float64: ret.prim_value as f64,
+      },
+      DLDataTypeCode_kDLFloat as c_int,
+    ),
+    4 => (TVMValue { v_int64: 0 }, TVMTypeCode_kNull as c_int),
+    11 => {
+      let val = ret
+        .box_value
+        .downcast::<String>()
+        .map_err(|_| ErrorKind::TryFromTVMRetValueError("String".to_string(), 11))?;
+      let val = CString::new(*val).map_err(|_| "Returned string contains a nul")?;
+      let v_str = RET_STR.with(|ret_str| {
+        *ret_str.borrow_mut() = val;
+        ret_str.borrow().as_ptr()
+      });
+      (TVMValue { v_str: v_str }, TVMTypeCode_kStr as c_int)
+    }
+    type_code => bail!(ErrorKind::FunctionCallError(format!(
+      "cannot return a value of type code {} through the C API",
+      type_code
+    ))),
+  })
+}
+
+/// Calls the function to which `func` refers. A `TVMFunctionHandle` points to an
+/// `Arc<PackedFunc>`, such as those returned by `TVMFuncGetGlobal`, `TVMFuncCreateFromCFunc`
+/// and `TVMBackendGetFuncFromEnv`.
+///
+/// The function's return value is stored in `ret_val` and `ret_type_code`. A returned string
+/// remains valid until the next call on the same thread. If the function fails or its return
+/// value cannot be converted, the error is available from `TVMGetLastError` and -1 is returned.
+/// -1 is also returned if `num_args` is negative or a required argument is null.
+#[no_mangle]
+pub extern "C" fn TVMFuncCall(
+  func: TVMFunctionHandle,
+  arg_values: *mut TVMValue,
+  type_codes: *mut c_int,
+  num_args: c_int,
+  ret_val: *mut TVMValue,
+  ret_type_code: *mut c_int,
+) -> c_int {
+  if func.is_null() || ret_val.is_null() || ret_type_code.is_null() {
+    set_last_error("TVMFuncCall called with a null argument");
+    return -1;
+  }
+  if num_args < 0 {
+    set_last_error(format!("TVMFuncCall called with {} arguments", num_args));
+    return -1;
+  }
+  if num_args > 0 && (arg_values.is_null() || type_codes.is_null()) {
+    set_last_error("TVMFuncCall called with null arguments");
+    return -1;
+  }
+  let func = unsafe { &*(func as *const Arc<PackedFunc>) };
+  let args = (0..num_args as isize)
+    .map(|i| unsafe { TVMArgValue::new(*arg_values.offset(i), *type_codes.offset(i) as i64) })
+    .collect::<Vec<TVMArgValue>>();
+  match func(&args).and_then(ret_tvm_value) {
+    Ok((val, type_code)) => {
+      unsafe {
+        *ret_val = val;
+        *ret_type_code = type_code;
+      }
+      0
+    }
+    Err(err) => {
+      set_last_error(call_error_message(err));
+      -1
+    }
+  }
+}
+
+/// Returns a new handle to `func`, which must be freed using `TVMFuncFree`.
+pub(super) fn new_func_handle(func: Arc<PackedFunc>) -> TVMFunctionHandle {
+  Box::into_raw(box func) as TVMFunctionHandle
+}
+
+/// Frees a handle returned by `TVMFuncGetGlobal` or `TVMFuncCreateFromCFunc`. The function
+/// itself is freed once it has no other handles and is not registered as a global function.
+///
+/// Returns -1 and sets the last error if `func` is null.
+#[no_mangle]
+pub extern "C" fn TVMFuncFree(func: TVMFunctionHandle) -> c_int {
+  if func.is_null() {
+    set_last_error("TVMFuncFree called with a null argument");
+    return -1;
+  }
+  drop(unsafe { Box::from_raw(func as *mut Arc<PackedFunc>) });
+  0
+}
+
+/// The resource of a function created by `TVMFuncCreateFromCFunc`, which is finalized when the
+/// function is freed.
+struct CFuncResource {
+  handle: *mut c_void,
+  fin: TVMPackedCFuncFinalizer,
+}
+
+// the caller of `TVMFuncCreateFromCFunc` makes the function safe to call from any thread
+unsafe impl Send for CFuncResource {}
+unsafe impl Sync for CFuncResource {}
+
+impl Drop for CFuncResource {
+  fn drop(&mut self) {
+    if let Some(fin) = self.fin {
+      unsafe { fin(self.handle) };
+    }
+  }
+}
+
+/// Sets `out` to a handle to a function which calls `func` with `resource_handle`. `func` may
+/// set its return value using `TVMCFuncSetReturn`. The handle must be freed using
+/// `TVMFuncFree`, after which `fin`, if not null, is called with `resource_handle` once the
+/// function is no longer registered as a global function.
+///
+/// Returns -1 and sets the last error if `func` or `out` is null.
+#[no_mangle]
+pub extern "C" fn TVMFuncCreateFromCFunc(
+  func: TVMPackedCFunc,
+  resource_handle: *mut c_void,
+  fin: TVMPackedCFuncFinalizer,
+  out: *mut TVMFunctionHandle,
+) -> c_int {
+  let func = match func {
+    Some(func) if !out.is_null() => func,
+    _ => {
+      set_last_error("TVMFuncCreateFromCFunc called with a null argument");
+      return -1;
+    }
+  };
+  let resource = CFuncResource {
+    handle: resource_handle,
+    fin: fin,
+  };
+  let wrapped: PackedFunc = box move |args: &[TVMArgValue]| {
+    let mut values = args.iter().map(|arg| arg.value).collect::<Vec<TVMValue>>();
+    let mut type_codes = args
+      .iter()
+      .map(|arg| arg.type_code as c_int)
+      .collect::<Vec<c_int>>();
+    let mut ret = TVMRetValue::from(());
+    // so that a message left over from an earlier error is not reported if `func` sets none
+    set_last_error("");
+    let status = unsafe {
+      func(
+        values.as_mut_ptr(),
+        type_codes.as_mut_ptr(),
+        args.len() as c_int,
+        &mut ret as *mut TVMRetValue as TVMRetValueHandle,
+        resource.handle,
+      )
+    };
+    if status != 0 {
+      let msg = match last_error() {
+        ref msg if msg.is_empty() => format!("function failed with status {}", status),
+        msg => msg,
+      };
+      bail!(ErrorKind::FunctionCallError(msg));
+    }
+    Ok(ret)
+  };
+  unsafe { *out = new_func_handle(Arc::new(wrapped)) };
+  0
+}
+
+/// Sets the return value of a function created by `TVMFuncCreateFromCFunc` to `value`, which
+/// may be an integer, a float, a string, or null. Strings are copied.
+///
+/// Returns -1 and sets the last error if an argument is null, `num_ret` is not 1, or the value
+/// is of another type.
+#[no_mangle]
+pub extern "C" fn TVMCFuncSetReturn(
+  ret: TVMRetValueHandle,
+  value: *mut TVMValue,
+  type_code: *mut c_int,
+  num_ret: c_int,
+) -> c_int {
+  if ret.is_null() || value.is_null() || type_code.is_null() {
+    set_last_error("TVMCFuncSetReturn called with a null argument");
+    return -1;
+  }
+  if num_ret != 1 {
+    set_last_error(format!("cannot return {} values", num_ret));
+    return -1;
+  }
+  let (value, type_code) = unsafe { (*value, *type_code) };
+  let ret_value = match type_code as u32 {
+    DLDataTypeCode_kDLInt => TVMRetValue::from(unsafe { value.v_int64 }),
+    DLDataTypeCode_kDLUInt => TVMRetValue::from(unsafe { value.v_int64 } as u64),
+    DLDataTypeCode_kDLFloat => TVMRetValue::from(unsafe { value.v_float64 }),
+    TVMTypeCode_kNull => TVMRetValue::from(()),
+    TVMTypeCode_kStr if unsafe { !value.v_str.is_null() } => TVMRetValue::from(
+      unsafe { CStr::from_ptr(value.v_str) }
+        .to_string_lossy()
+        .into_owned(),
+    ),
+    _ => {
+      set_last_error(format!(
+        "cannot return a value of type code {} through the C API",
+        type_code
+      ));
+      return -1;
+    }
+  };
+  unsafe { *(ret as *mut TVMRetValue) = ret_value };
+  0
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{mem, os::raw::c_char, ptr};
+
+  use super::*;
+  use runtime::{TVMAPISetLastError, TVMGetLastError};
+
+  extern "C" fn failing_func(
+    _args: *const TVMValue,
+    _type_codes: *const c_int,
+    _num_args: c_int,
+  ) -> c_int {
+    TVMAPISetLastError(b"assertion failed\0".as_ptr() as *const c_char);
+    -1
+  }
+
+  extern "C" fn silently_failing_func(
+    _args: *const TVMValue,
+    _type_codes: *const c_int,
+    _num_args: c_int,
+  ) -> c_int {
+    -2
+  }
+
+  unsafe extern "C" fn add_resource(
+    args: *mut TVMValue,
+    _type_codes: *mut c_int,
+    _num_args: c_int,
+    ret: TVMRetValueHandle,
+    resource_handle: *mut c_void,
+  ) -> c_int {
+    let mut sum = TVMValue {
+      v_int64: (*args).v_int64 + *(resource_handle as *const i64),
+    };
+    let mut type_code = DLDataTypeCode_kDLInt as c_int;
+    TVMCFuncSetReturn(ret, &mut sum, &mut type_code, 1)
+  }
+
+  unsafe extern "C" fn finalize_resource(resource_handle: *mut c_void) {
+    *(resource_handle as *mut i64) = -1;
+  }
+
+  fn get_last_error() -> String {
+    unsafe {
+      CStr::from_ptr(TVMGetLastError())
+        .to_string_lossy()
+        .into_owned()
+    }
+  }
+
+  #[test]
+  fn test_backend_func_error() {
+    let func = wrap_backend_packed_func(failing_func);
+    match func(&[]) {
+      Err(Error(ErrorKind::FunctionCallError(msg), _)) => assert_eq!(msg, "assertion failed"),
+      _ => panic!("expected a FunctionCallError"),
+    }
+    assert_eq!(get_last_error(), "assertion failed");
+
+    // the message of the earlier error is not reported again
+    let func = wrap_backend_packed_func(silently_failing_func);
+    match func(&[]) {
+      Err(Error(ErrorKind::FunctionCallError(msg), _)) => {
+        assert_eq!(msg, "function failed with status -2")
+      }
+      _ => panic!("expected a FunctionCallError"),
+    }
+  }
+
+  #[test]
+  fn test_func_create_from_c_func() {
+    let mut resource = 2i64;
+    let resource_handle = &mut resource as *mut i64 as *mut c_void;
+    let mut handle = ptr::null_mut();
+    let status = TVMFuncCreateFromCFunc(
+      Some(add_resource),
+      resource_handle,
+      Some(finalize_resource),
+      &mut handle,
+    );
+    assert_eq!(status, 0);
+
+    let mut args = [TVMValue { v_int64: 40 }];
+    let mut type_codes = [DLDataTypeCode_kDLInt as c_int];
+    let (mut ret_val, mut ret_type_code) = (unsafe { mem::zeroed() }, 0);
+    let status = TVMFuncCall(
+      handle,
+      args.as_mut_ptr(),
+      type_codes.as_mut_ptr(),
+      1,
+      &mut ret_val,
+      &mut ret_type_code,
+    );
+    assert_eq!(status, 0);
+    assert_eq!(unsafe { ret_val.v_int64 }, 42);
+
+    assert_eq!(TVMFuncFree(handle), 0);
+    assert_eq!(resource, -1);
+
+    let status = TVMFuncCreateFromCFunc(None, ptr::null_mut(), None, &mut handle);
+    assert_eq!(status, -1);
+    assert!(get_last_error().contains("null argument"));
+  }
+
+  #[test]
+  fn test_func_call_error() {
+    let func: Arc<PackedFunc> = Arc::new(box |_args: &[TVMArgValue]| {
+      bail!(ErrorKind::FunctionCallError("invalid shape".to_string()))
+    });
+    let handle = &func as *const Arc<PackedFunc> as TVMFunctionHandle;
+    let (mut ret_val, mut ret_type_code) = (unsafe { mem::zeroed() }, 0);
+    let status = TVMFuncCall(
+      handle,
+      ptr::null_mut(),
+      ptr::null_mut(),
+      0,
+      &mut ret_val,
+      &mut ret_type_code,
+    );
+    assert_eq!(status, -1);
+    assert_eq!(get_last_error(), "invalid shape");
+  }
+
+  #[test]
+  fn test_func_call_invalid_args() {
+    let func: Arc<PackedFunc> = Arc::new(box |_args: &[TVMArgValue]| Ok(TVMRetValue::default()));
+    let handle = &func as *const Arc<PackedFunc> as TVMFunctionHandle;
+    let (mut ret_val, mut ret_type_code) = (unsafe { mem::zeroed() }, 0);
+    let mut call = |handle: TVMFunctionHandle, num_args: c_int, ret_val: *mut TVMValue| {
+      TVMFuncCall(
+        handle,
+        ptr::null_mut(),
+        ptr::null_mut(),
+        num_args,
+        ret_val,
+        &mut ret_type_code,
+      )
+    };
+    assert_eq!(call(ptr::null_mut(), 0, &mut ret_val), -1);
+    assert!(get_last_error().contains("null argument"));
+    assert_eq!(call(handle, 0, ptr::null_mut()), -1);
+    assert_eq!(call(handle, -1, &mut ret_val), -1);
+    assert!(get_last_error().contains("-1 arguments"));
+    assert_eq!(call(handle, 1, &mut ret_val), -1);
+    assert!(get_last_error().contains("null arguments"));
+    assert_eq!(call(handle, 0, &mut ret_val), 0);
+  }
+
+  #[test]
+  fn test_func_call_ret_val() {
+    let call = |ret: fn() -> TVMRetValue| {
+      let func: Arc<PackedFunc> = Arc::new(box move |_args: &[TVMArgValue]| Ok(ret()));
+      let handle = &func as *const Arc<PackedFunc> as TVMFunctionHandle;
+      let (mut ret_val, mut ret_type_code) = (unsafe { mem::zeroed() }, 0);
+      let status = TVMFuncCall(
+        handle,
+        ptr::null_mut(),
+        ptr::null_mut(),
+        0,
+        &mut ret_val,
+        &mut ret_type_code,
+      );
+      assert_eq!(status, 0);
+      (ret_val, ret_type_code as u32)
+    };
+
+    let (val, type_code) = call(|| TVMRetValue::from(-3i64));
+    assert_eq!(type_code, DLDataTypeCode_kDLInt);
+    assert_eq!(unsafe { val.v_int64 }, -3);
+
+    let (val, type_code) = call(|| TVMRetValue::from(2f64));
+    assert_eq!(type_code, DLDataTypeCode_kDLFloat);
+    assert_eq!(unsafe { val.v_float64 }, 2.);
+
+    let (val, type_code) = call(|| TVMRetValue::from("answer".to_string()));
+    assert_eq!(type_code, TVMTypeCode_kStr);
+    assert_eq!(
+      unsafe { CStr::from_ptr(val.v_str) }.to_str().unwrap(),
+      "answer"
+    );
+
+    let (_, type_code) = call(|| TVMRetValue::from(()));
+    assert_eq!(type_code, TVMTypeCode_kNull);
+  }
+
+  #[test]
+  fn test_arg_conversion_errors() {
+    assert_eq!(u8::try_from(TVMArgValue::from(255i64)).unwrap(), 255);
+    let err = u8::try_from(TVMArgValue::from(256i64)).err().unwrap();
+    assert!(err.to_string().contains("the value 256 is out of range"));
+    assert!(u32::try_from(TVMArgValue::from(-1i64)).is_err());
+    assert!(i32::try_from(TVMArgValue::from(i64::from(i32::max_value()) + 1)).is_err());
+
+    let null_str = TVMArgValue::new(TVMValue { v_str: ptr::null() }, TVMTypeCode_kStr as i64);
+    assert!(String::try_from(null_str).is_err());
   }
 }
@@ -0,0 +1,409 @@
+use std::{
+  io::{self, Read},
+  slice,
+};
+
+use super::{
+  graph::{num_elements, parse_tensor_header, NDARRAY_LIST_MAGIC, TENSOR_HEADER_LEN},
+  DataType, Storage, TVMContext, Tensor,
+};
+use errors::{Error, ErrorKind, Result};
+
+/// Decodes a param dict saved using `nnvm.compiler.save_param_dict` from an `io::Read` one
+/// param at a time, so that neither the whole file nor all of the decoded params need to be
+/// held in memory at once.
+///
+/// Errors name the param being read and the byte offset at which reading failed.
+///
+/// # Examples
+///
+/// ```
+/// let file = BufReader::new(File::open("deploy_param.params").unwrap());
+/// for param in ParamReader::new(file).unwrap() {
+///   let (name, tensor) = param.unwrap();
+///   // ...
+/// }
+///
+/// // or, to decode directly into the storage of the executor's inputs
+/// exec.load_params_from(BufReader::new(File::open("deploy_param.params").unwrap())).unwrap();
+/// ```
+pub struct ParamReader<R: Read> {
+  reader: R,
+  /// The number of bytes read so far.
+  offset: u64,
+  /// The names of the params which have not yet been read, in reverse order.
+  names: Vec<String>,
+}
+
+/// The metadata which precedes the data of a param.
+pub(super) struct ParamHeader {
+  ctx: TVMContext,
+  pub(super) dtype: DataType,
+  pub(super) shape: Vec<i64>,
+  /// The number of elements.
+  size: usize,
+  pub(super) num_bytes: usize,
+}
+
+impl<R: Read> ParamReader<R> {
+  /// Reads the header and param names from `reader`.
+  pub fn new(reader: R) -> Result<Self> {
+    let mut params = ParamReader {
+      reader: reader,
+      offset: 0,
+      names: Vec::new(),
+    };
+    let magic = params.read_le(8, "the magic number")?;
+    params.ensure(
+      magic == NDARRAY_LIST_MAGIC,
+      8,
+      format!("invalid magic number {:#x}", magic),
+    )?;
+    let reserved = params.read_le(8, "the reserved field")?;
+    params.ensure(
+      reserved == 0,
+      8,
+      format!("invalid reserved field {:#x}", reserved),
+    )?;
+    let num_names = params.read_le(8, "the number of params")?;
+    for i in 0..num_names {
+      let len = params.read_le(8, &format!("the length of the name of param {}", i))?;
+      // the name is read through `take` so that an invalid length fails at the end of the
+      // input rather than allocating that many bytes up front
+      let mut name = Vec::new();
+      let num_read = (&mut params.reader).take(len).read_to_end(&mut name)? as u64;
+      params.offset += num_read;
+      params.ensure(
+        num_read == len,
+        0,
+        format!(
+          "unexpected end of input while reading {} bytes of the name of param {}",
+          len, i
+        ),
+      )?;
+      let name = String::from_utf8(name);
+      params.ensure(
+        name.is_ok(),
+        len,
+        format!("the name of param {} is not valid UTF-8", i),
+      )?;
+      params.names.push(name.unwrap());
+    }
+    let num_tensors = params.read_le(8, "the number of tensors")?;
+    params.ensure(
+      num_tensors == num_names,
+      8,
+      format!("found {} names but {} tensors", num_names, num_tensors),
+    )?;
+    params.names.reverse();
+    Ok(params)
+  }
+
+  /// Returns the number of params which have not yet been read.
+  pub fn remaining(&self) -> usize {
+    self.names.len()
+  }
+
+  /// Returns the number of bytes read so far.
+  pub fn offset(&self) -> u64 {
+    self.offset
+  }
+
+  /// Reads the name and metadata of the next param, after which its data must be read using
+  /// `read_data` or `skip_data`.
+  pub(super) fn read_header(&mut self) -> Result<Option<(String, ParamHeader)>> {
+    let name = match self.names.pop() {
+      Some(name) => name,
+      None => return Ok(None),
+    };
+    let what = |field: &str| format!("the {} of param `{}`", field, name);
+    let mut header = [0u8; TENSOR_HEADER_LEN];
+    self.read_exact(&mut header, &what("header"))?;
+    let (ctx, ndim, dtype) = match parse_tensor_header(&header) {
+      Ok(header) => header,
+      Err(msg) => bail!(self.error(
+        TENSOR_HEADER_LEN as u64,
+        format!("{} for param `{}`", msg, name)
+      )),
+    };
+    // the shape is not preallocated so that an invalid `ndim` fails at the end of the input
+    let mut shape = Vec::new();
+    for _ in 0..ndim {
+      shape.push(self.read_le(8, &what("shape"))? as i64);
+    }
+    let size = num_elements(&shape);
+    let expected_num_bytes = size.and_then(|size| size.checked_mul(dtype.itemsize()));
+    self.ensure(
+      expected_num_bytes.is_some(),
+      8 * ndim as u64,
+      format!("invalid shape {:?} for param `{}`", shape, name),
+    )?;
+    let num_bytes = self.read_le(8, &what("data length"))?;
+    self.ensure(
+      Some(num_bytes) == expected_num_bytes.map(|num_bytes| num_bytes as u64),
+      8,
+      format!(
+        "param `{}` should have {} bytes of data but has {}",
+        name,
+        expected_num_bytes.unwrap(),
+        num_bytes
+      ),
+    )?;
+    Ok(Some((
+      name,
+      ParamHeader {
+        ctx: ctx,
+        dtype: dtype,
+        shape: shape,
+        size: size.unwrap(),
+        num_bytes: num_bytes as usize,
+      },
+    )))
+  }
+
+  pub(super) fn read_data(&mut self, name: &str, buf: &mut [u8]) -> Result<()> {
+    self.read_exact(buf, &format!("the data of param `{}`", name))
+  }
+
+  pub(super) fn skip_data(&mut self, name: &str, header: &ParamHeader) -> Result<()> {
+    let num_bytes = header.num_bytes as u64;
+    let num_skipped = io::copy(&mut (&mut self.reader).take(num_bytes), &mut io::sink())?;
+    self.offset += num_skipped;
+    self.ensure(
+      num_skipped == num_bytes,
+      0,
+      format!("unexpected end of input in the data of param `{}`", name),
+    )
+  }
+
+  /// Reads the data of a param into newly allocated storage.
+  fn read_tensor(&mut self, name: &str, header: ParamHeader) -> Result<Tensor<'static>> {
+    let data = Storage::new(header.num_bytes, Some(header.dtype.align()))?;
+    self.read_data(name, unsafe {
+      slice::from_raw_parts_mut(data.as_mut_ptr(), header.num_bytes)
+    })?;
+    Ok(Tensor {
+      data: data,
+      ctx: header.ctx,
+      dtype: header.dtype,
+      size: header.size,
+      shape: header.shape,
+      strides: None,
+      byte_offset: 0,
+    })
+  }
+
+  /// Reads a little-endian unsigned integer of `num_bytes` bytes.
+  fn read_le(&mut self, num_bytes: usize, what: &str) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    self.read_exact(&mut buf[..num_bytes], what)?;
+    Ok(
+      buf[..num_bytes]
+        .iter()
+        .rev()
+        .fold(0, |val, &byte| val << 8 | byte as u64),
+    )
+  }
+
+  fn read_exact(&mut self, buf: &mut [u8], what: &str) -> Result<()> {
+    match self.reader.read_exact(buf) {
+      Ok(()) => {
+        self.offset += buf.len() as u64;
+        Ok(())
+      }
+      Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+        bail!(ErrorKind::LoadGraphParamsError(format!(
+          "unexpected end of input while reading {} bytes of {} at byte {}",
+          buf.len(),
+          what,
+          self.offset
+        )))
+      }
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  /// Fails with `msg` if `cond` is false. `field_len` is the length of the field just read,
+  /// whose offset is reported.
+  fn ensure(&self, cond: bool, field_len: u64, msg: String) -> Result<()> {
+    ensure!(cond, self.error(field_len, msg));
+    Ok(())
+  }
+
+  /// Returns an error with `msg` at the offset of the field of `field_len` bytes just read.
+  fn error(&self, field_len: u64, msg: String) -> Error {
+    ErrorKind::LoadGraphParamsError(format!("{} at byte {}", msg, self.offset - field_len)).into()
+  }
+}
+
+impl<R: Read> Iterator for ParamReader<R> {
+  type Item = Result<(String, Tensor<'static>)>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let (name, header) = match self.read_header() {
+      Ok(Some(param)) => param,
+      Ok(None) => return None,
+      Err(err) => {
+        self.names.clear(); // the position in the input is unknown
+        return Some(Err(err));
+      }
+    };
+    let tensor = self.read_tensor(&name, header);
+    if tensor.is_err() {
+      self.names.clear();
+    }
+    Some(tensor.map(|tensor| (name, tensor)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::HashMap, convert::TryFrom, io::Cursor};
+
+  use ndarray::Array;
+
+  use super::*;
+  use runtime::{
+    graph::tests::{TestModule, TEST_GRAPH_JSON},
+    save_param_dict, Graph, GraphExecutor,
+  };
+
+  fn test_param_bytes() -> Vec<u8> {
+    let w = Array::from_shape_vec((2, 2), vec![1f32, 2., 3., 4.]).unwrap();
+    let unused = Array::from_vec(vec![5i32, 6]);
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), Tensor::from(&w));
+    params.insert("unused".to_string(), Tensor::from(&unused));
+    save_param_dict(&params).unwrap()
+  }
+
+  #[test]
+  fn test_param_reader() {
+    let bytes = test_param_bytes();
+    let mut params = ParamReader::new(Cursor::new(&bytes))
+      .unwrap()
+      .collect::<Result<HashMap<String, Tensor>>>()
+      .unwrap();
+    assert_eq!(params.len(), 2);
+    assert_eq!(
+      params.remove("w").unwrap().to_vec::<f32>(),
+      vec![1f32, 2., 3., 4.]
+    );
+    assert_eq!(params["unused"].shape, vec![2]);
+  }
+
+  #[test]
+  fn test_load_params_from() {
+    let graph = Graph::try_from(TEST_GRAPH_JSON).unwrap();
+    let mut exec = GraphExecutor::new(graph, &TestModule).unwrap();
+    exec
+      .load_params_from(Cursor::new(test_param_bytes()))
+      .unwrap();
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.run().unwrap();
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 5., 10., 17.]
+    );
+    assert_eq!(exec.params().len(), 1);
+  }
+
+  #[test]
+  fn test_param_reader_truncated() {
+    let bytes = test_param_bytes();
+    let mut params = ParamReader::new(Cursor::new(&bytes[..bytes.len() - 1])).unwrap();
+    let errs = params
+      .by_ref()
+      .filter_map(|param| param.err())
+      .collect::<Vec<_>>();
+    assert_eq!(errs.len(), 1);
+    let msg = errs[0].to_string();
+    assert!(msg.contains("unexpected end of input while reading"));
+    assert!(msg.contains("the data of param"));
+    // the data of the last param, which is either `w` or `unused`, starts 16 or 8 bytes from the end
+    assert!(
+      msg.contains(&format!("at byte {}", bytes.len() - 16))
+        || msg.contains(&format!("at byte {}", bytes.len() - 8))
+    );
+    assert_eq!(params.remaining(), 0);
+
+    let err = ParamReader::new(Cursor::new(&bytes[..12])).err().unwrap();
+    assert!(err
+      .to_string()
+      .contains("reading 8 bytes of the reserved field at byte 8"));
+  }
+
+  #[test]
+  fn test_param_reader_invalid() {
+    let w = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), Tensor::from(&w));
+    let bytes = save_param_dict(&params).unwrap();
+    // the name of `w` starts at byte 24, its header at byte 41, and its shape at byte 73
+    let read_err = |bytes: &[u8]| {
+      ParamReader::new(Cursor::new(bytes))
+        .and_then(|mut params| params.next().unwrap())
+        .err()
+        .unwrap()
+        .to_string()
+    };
+
+    let mut long_name = bytes.clone();
+    long_name[24..32].copy_from_slice(&[0xff; 8]);
+    assert!(read_err(&long_name).contains("unexpected end of input"));
+
+    let mut many_dims = bytes.clone();
+    many_dims[65..69].copy_from_slice(&[0xff; 4]);
+    assert!(read_err(&many_dims).contains("unexpected end of input"));
+
+    let mut negative_dim = bytes.clone();
+    negative_dim[73..81].copy_from_slice(&[0xff; 8]); // -1
+    assert!(read_err(&negative_dim).contains("invalid shape [-1] for param `w` at byte 73"));
+
+    let mut large_dim = bytes.clone();
+    large_dim[73..81].copy_from_slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+    assert!(read_err(&large_dim).contains("invalid shape"));
+
+    let mut bad_dtype = bytes.clone();
+    bad_dtype[69] = 42;
+    assert!(read_err(&bad_dtype).contains("invalid dtype"));
+  }
+
+  #[test]
+  fn test_param_reader_int24() {
+    let w = Array::from_vec(vec![1i32, 2, 3, 4]);
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), Tensor::from(&w));
+    let mut bytes = save_param_dict(&params).unwrap();
+    // make `w` an int24 tensor, whose data is the first 12 bytes of that of the int32 tensor
+    bytes[70] = 24;
+    bytes[81] = 12;
+    bytes.truncate(bytes.len() - 4);
+
+    let (name, w) = ParamReader::new(Cursor::new(&bytes))
+      .unwrap()
+      .next()
+      .unwrap()
+      .unwrap();
+    assert_eq!(name, "w");
+    assert_eq!(w.dtype.to_string(), "int24");
+    assert_eq!(w.data.size(), 12);
+    assert_eq!(w.data.align(), 4);
+  }
+
+  #[test]
+  fn test_load_params_from_mismatched() {
+    let graph = Graph::try_from(TEST_GRAPH_JSON).unwrap();
+    let mut exec = GraphExecutor::new(graph, &TestModule).unwrap();
+    let w = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), Tensor::from(&w));
+    let err = exec
+      .load_params_from(Cursor::new(save_param_dict(&params).unwrap()))
+      .err()
+      .unwrap();
+    assert!(err.to_string().contains("shape [2, 2]"));
+    assert!(exec.params().is_empty());
+  }
+}
@@ -0,0 +1,207 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde_json;
+
+use super::Node;
+use errors::Result;
+
+/// Records the time taken by each op over one or more runs of a `GraphExecutor`.
+///
+/// # Examples
+///
+/// ```
+/// let mut profiler = Profiler::new();
+/// for _ in 0..10 {
+///   exec.run_profiled(&mut profiler).unwrap();
+/// }
+/// println!("{}", profiler.report());
+/// ```
+#[derive(Default)]
+pub struct Profiler {
+  /// The profiles of each op, in order of first execution.
+  ops: Vec<OpProfile>,
+  /// Maps node names to indices into `ops`.
+  op_indices: HashMap<String, usize>,
+}
+
+/// The times recorded for a single op.
+pub struct OpProfile {
+  pub node_name: String,
+  pub func_name: String,
+  pub durations: Vec<Duration>,
+}
+
+/// Summary statistics of an `OpProfile`. Times are in microseconds.
+#[derive(Serialize, Debug)]
+pub struct OpStats {
+  pub node_name: String,
+  pub func_name: String,
+  pub num_runs: usize,
+  pub min_us: f64,
+  pub mean_us: f64,
+  pub p99_us: f64,
+}
+
+impl Profiler {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Records that executing `node` took `duration`.
+  pub(super) fn record(&mut self, node: &Node, duration: Duration) {
+    let ops = &mut self.ops;
+    let idx = *self.op_indices.entry(node.name.clone()).or_insert_with(|| {
+      ops.push(OpProfile {
+        node_name: node.name.clone(),
+        func_name: node
+          .attrs
+          .as_ref()
+          .and_then(|attrs| attrs.get("func_name"))
+          .cloned()
+          .unwrap_or_default(),
+        durations: Vec::new(),
+      });
+      ops.len() - 1
+    });
+    ops[idx].durations.push(duration);
+  }
+
+  /// Returns the recorded profiles in order of execution.
+  pub fn ops(&self) -> &[OpProfile] {
+    &self.ops
+  }
+
+  /// Returns summary statistics for each op in order of execution.
+  pub fn stats(&self) -> Vec<OpStats> {
+    self.ops.iter().map(OpProfile::stats).collect()
+  }
+
+  /// Clears all recorded times.
+  pub fn reset(&mut self) {
+    self.ops.clear();
+    self.op_indices.clear();
+  }
+
+  /// Renders the summary statistics as a table.
+  pub fn report(&self) -> String {
+    let stats = self.stats();
+    let total_us = stats.iter().map(|op| op.mean_us).sum::<f64>();
+    let mut report = format!(
+      "{:<32} {:<32} {:>6} {:>12} {:>12} {:>12} {:>7}\n",
+      "Node", "Function", "Runs", "Min (us)", "Mean (us)", "P99 (us)", "Time %"
+    );
+    for op in stats.iter() {
+      report.push_str(&format!(
+        "{:<32} {:<32} {:>6} {:>12.2} {:>12.2} {:>12.2} {:>7.2}\n",
+        op.node_name,
+        op.func_name,
+        op.num_runs,
+        op.min_us,
+        op.mean_us,
+        op.p99_us,
+        if total_us > 0. {
+          100. * op.mean_us / total_us
+        } else {
+          0.
+        }
+      ));
+    }
+    report.push_str(&format!("Total mean time: {:.2} us\n", total_us));
+    report
+  }
+
+  /// Serializes the summary statistics as a JSON array.
+  pub fn to_json(&self) -> Result<String> {
+    Ok(serde_json::to_string(&self.stats())?)
+  }
+}
+
+impl OpProfile {
+  /// Returns the `percentile`th (nearest-rank) duration in microseconds.
+  pub fn percentile(&self, percentile: f64) -> f64 {
+    if self.durations.len() == 0 {
+      return 0.;
+    }
+    let mut durations = self.durations.clone();
+    durations.sort();
+    let rank = (percentile / 100. * durations.len() as f64).ceil() as usize;
+    as_micros(durations[rank.max(1).min(durations.len()) - 1])
+  }
+
+  /// Returns summary statistics of the recorded durations.
+  pub fn stats(&self) -> OpStats {
+    OpStats {
+      node_name: self.node_name.clone(),
+      func_name: self.func_name.clone(),
+      num_runs: self.durations.len(),
+      min_us: self.percentile(0.),
+      mean_us: self.durations.iter().map(|&d| as_micros(d)).sum::<f64>()
+        / self.durations.len().max(1) as f64,
+      p99_us: self.percentile(99.),
+    }
+  }
+}
+
+fn as_micros(duration: Duration) -> f64 {
+  duration.as_secs() as f64 * 1e6 + duration.subsec_nanos() as f64 / 1e3
+}
+
+#[cfg(test)]
+mod tests {
+  use std::convert::TryFrom;
+
+  use ndarray::Array;
+
+  use super::*;
+  use runtime::{
+    graph::tests::{TestModule, TEST_GRAPH_JSON},
+    Graph, GraphExecutor, Tensor,
+  };
+
+  #[test]
+  fn test_run_profiled() {
+    let graph = Graph::try_from(TEST_GRAPH_JSON).unwrap();
+    let mut exec = GraphExecutor::new(graph, &TestModule).unwrap();
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.set_input("w", Tensor::from(&x)).unwrap();
+
+    let mut profiler = Profiler::new();
+    exec.run_profiled(&mut profiler).unwrap();
+    exec.run_profiled(&mut profiler).unwrap();
+    assert_eq!(
+      exec.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 5., 10., 17.]
+    );
+
+    let stats = profiler.stats();
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats[0].node_name, "mul");
+    assert_eq!(stats[1].func_name, "add_one");
+    assert!(stats.iter().all(|op| op.num_runs == 2));
+    assert!(stats.iter().all(|op| op.min_us <= op.p99_us));
+
+    let report = profiler.report();
+    assert!(report.contains("mul") && report.contains("add_one"));
+    let json = serde_json::from_str::<serde_json::Value>(&profiler.to_json().unwrap()).unwrap();
+    assert_eq!(json[1]["node_name"], "add_one");
+
+    profiler.reset();
+    assert!(profiler.ops().is_empty());
+  }
+
+  #[test]
+  fn test_percentile() {
+    let profile = OpProfile {
+      node_name: "op".to_string(),
+      func_name: "op".to_string(),
+      durations: (1..101).rev().map(Duration::from_millis).collect(),
+    };
+    assert_eq!(profile.percentile(99.), 99000.);
+    assert_eq!(profile.percentile(50.), 50000.);
+    assert_eq!(profile.percentile(0.), 1000.);
+    let stats = profile.stats();
+    assert_eq!(stats.min_us, 1000.);
+    assert_eq!(stats.mean_us, 50500.);
+  }
+}
@@ -0,0 +1,250 @@
+use std::{
+  cell::RefCell,
+  collections::HashMap,
+  ffi::{CStr, CString},
+  os::raw::{c_char, c_int},
+  ptr,
+  sync::{Arc, Mutex},
+};
+
+use super::{packed_func::new_func_handle, set_last_error, PackedFunc, TVMArgValue};
+use errors::Result;
+use ffi::runtime::TVMFunctionHandle;
+
+lazy_static! {
+  /// The global functions. A function which is overridden or removed is freed once it has no
+  /// handles, such as those returned by `TVMFuncGetGlobal`.
+  static ref GLOBAL_FUNCTIONS: Mutex<HashMap<String, Arc<PackedFunc>>> =
+    Mutex::new(HashMap::new());
+}
+
+/// Registers `func` as the global function `name`, which can then be found using
+/// `get_global` or, from C and TVM functions, `TVMFuncGetGlobal`.
+///
+/// Fails if `name` is already registered unless `override_existing` is set.
+///
+/// # Examples
+///
+/// ```
+/// register_global("my_relu", box |args: &[TVMArgValue]| {
+///   // ...
+///   Ok(TVMRetValue::default())
+/// }, false).unwrap();
+/// let relu = get_global("my_relu").unwrap();
+/// ```
+pub fn register_global<S: Into<String>>(
+  name: S,
+  func: PackedFunc,
+  override_existing: bool,
+) -> Result<()> {
+  register_global_arc(name.into(), Arc::new(func), override_existing)
+}
+
+fn register_global_arc(name: String, func: Arc<PackedFunc>, override_existing: bool) -> Result<()> {
+  let mut funcs = GLOBAL_FUNCTIONS.lock().unwrap();
+  ensure!(
+    override_existing || !funcs.contains_key(&name),
+    "Global function `{}` is already registered",
+    name
+  );
+  funcs.insert(name, func);
+  Ok(())
+}
+
+/// Returns the global function named `name`, if it exists.
+pub fn get_global<S: AsRef<str>>(name: S) -> Option<PackedFunc> {
+  GLOBAL_FUNCTIONS
+    .lock()
+    .unwrap()
+    .get(name.as_ref())
+    .map(|func| {
+      let func = Arc::clone(func);
+      let wrapped: PackedFunc = box move |args: &[TVMArgValue]| func(args);
+      wrapped
+    })
+}
+
+/// Returns the names of the global functions in sorted order.
+pub fn list_global_names() -> Vec<String> {
+  let mut names = GLOBAL_FUNCTIONS
+    .lock()
+    .unwrap()
+    .keys()
+    .cloned()
+    .collect::<Vec<String>>();
+  names.sort();
+  names
+}
+
+/// Removes the global function named `name`. Returns `false` if it did not exist.
+/// The function is freed once no handle or function returned by `get_global` refers to it.
+pub fn remove_global<S: AsRef<str>>(name: S) -> bool {
+  GLOBAL_FUNCTIONS
+    .lock()
+    .unwrap()
+    .remove(name.as_ref())
+    .is_some()
+}
+
+/// Returns the global function named `name` as shared by its handles.
+pub(super) fn get_global_arc(name: &str) -> Option<Arc<PackedFunc>> {
+  GLOBAL_FUNCTIONS.lock().unwrap().get(name).cloned()
+}
+
+/// Registers the function to which the handle `f` refers, as by `register_global`. The handle
+/// can be freed once the function is registered.
+/// Returns -1 and sets the last error if registration fails or an argument is null.
+#[no_mangle]
+pub extern "C" fn TVMFuncRegisterGlobal(
+  name: *const c_char,
+  f: TVMFunctionHandle,
+  override_: c_int,
+) -> c_int {
+  if name.is_null() || f.is_null() {
+    set_last_error("TVMFuncRegisterGlobal called with a null argument");
+    return -1;
+  }
+  let name = unsafe { CStr::from_ptr(name).to_string_lossy().into_owned() };
+  let func = unsafe { Arc::clone(&*(f as *const Arc<PackedFunc>)) };
+  match register_global_arc(name, func, override_ != 0) {
+    Ok(()) => 0,
+    Err(err) => {
+      set_last_error(err.to_string());
+      -1
+    }
+  }
+}
+
+/// Sets `out` to a handle to the global function named `name`, or to null if there is no such
+/// function. The handle must be freed using `TVMFuncFree`. Until then, it refers to the same
+/// function even if the global function is overridden or removed.
+///
+/// Returns -1 and sets the last error if an argument is null.
+#[no_mangle]
+pub extern "C" fn TVMFuncGetGlobal(name: *const c_char, out: *mut TVMFunctionHandle) -> c_int {
+  if name.is_null() || out.is_null() {
+    set_last_error("TVMFuncGetGlobal called with a null argument");
+    return -1;
+  }
+  let name = unsafe { CStr::from_ptr(name).to_string_lossy() };
+  unsafe { *out = get_global_arc(&name).map_or(ptr::null_mut(), new_func_handle) };
+  0
+}
+
+thread_local! {
+  /// The names returned by the last call to `TVMFuncListGlobalNames` on this thread.
+  static GLOBAL_NAMES: RefCell<(Vec<CString>, Vec<*const c_char>)> =
+    RefCell::new((Vec::new(), Vec::new()));
+}
+
+/// Sets `out_array` to the names of the global functions. The names remain valid until the next
+/// call on the same thread.
+///
+/// Returns -1 and sets the last error if an argument is null.
+#[no_mangle]
+pub extern "C" fn TVMFuncListGlobalNames(
+  out_size: *mut c_int,
+  out_array: *mut *mut *const c_char,
+) -> c_int {
+  if out_size.is_null() || out_array.is_null() {
+    set_last_error("TVMFuncListGlobalNames called with a null argument");
+    return -1;
+  }
+  GLOBAL_NAMES.with(|names| {
+    let (ref mut names, ref mut ptrs) = *names.borrow_mut();
+    *names = list_global_names()
+      .into_iter()
+      .filter_map(|name| CString::new(name).ok())
+      .collect();
+    *ptrs = names.iter().map(|name| name.as_ptr()).collect();
+    unsafe {
+      *out_size = ptrs.len() as c_int;
+      *out_array = ptrs.as_mut_ptr();
+    }
+  });
+  0
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{convert::TryFrom, mem};
+
+  use super::*;
+  use runtime::{TVMFuncCall, TVMFuncFree, TVMRetValue};
+
+  fn const_func(val: i64) -> PackedFunc {
+    box move |_args: &[TVMArgValue]| Ok(TVMRetValue::from(val))
+  }
+
+  fn call(func: PackedFunc) -> i64 {
+    i64::try_from(func(&[]).unwrap()).unwrap()
+  }
+
+  fn call_handle(handle: TVMFunctionHandle) -> i64 {
+    let (mut ret_val, mut ret_type_code) = (unsafe { mem::zeroed() }, 0);
+    let args = (ptr::null_mut(), ptr::null_mut());
+    assert_eq!(
+      TVMFuncCall(handle, args.0, args.1, 0, &mut ret_val, &mut ret_type_code),
+      0
+    );
+    unsafe { ret_val.v_int64 }
+  }
+
+  #[test]
+  fn test_register_global() {
+    register_global("test.answer", const_func(42), false).unwrap();
+    let answer = get_global("test.answer").unwrap();
+    assert_eq!(i64::try_from(answer(&[]).unwrap()).unwrap(), 42);
+    assert!(list_global_names().contains(&"test.answer".to_string()));
+
+    assert!(register_global("test.answer", const_func(0), false).is_err());
+    register_global("test.answer", const_func(7), true).unwrap();
+    assert_eq!(call(get_global("test.answer").unwrap()), 7);
+    // functions which were already obtained are unaffected
+    assert_eq!(call(answer), 42);
+
+    assert!(remove_global("test.answer"));
+    assert!(get_global("test.answer").is_none());
+    assert!(!remove_global("test.answer"));
+  }
+
+  #[test]
+  fn test_global_c_api() {
+    register_global("test.c_api", const_func(1), false).unwrap();
+    let name = CString::new("test.c_api").unwrap();
+    let alias = CString::new("test.c_api_alias").unwrap();
+    let mut handle = ptr::null_mut();
+    assert_eq!(TVMFuncGetGlobal(name.as_ptr(), &mut handle), 0);
+    assert!(!handle.is_null());
+    assert_eq!(TVMFuncRegisterGlobal(alias.as_ptr(), handle, 0), 0);
+    assert_eq!(TVMFuncRegisterGlobal(alias.as_ptr(), handle, 0), -1);
+    assert_eq!(call(get_global("test.c_api_alias").unwrap()), 1);
+    assert_eq!(call_handle(handle), 1);
+
+    // the handle outlives the function being overridden and removed
+    register_global("test.c_api", const_func(2), true).unwrap();
+    assert_eq!(call_handle(handle), 1);
+    assert!(remove_global("test.c_api"));
+    assert_eq!(call_handle(handle), 1);
+    assert_eq!(TVMFuncFree(handle), 0);
+    register_global("test.c_api", const_func(1), false).unwrap();
+
+    let (mut size, mut names) = (0, ptr::null_mut());
+    assert_eq!(TVMFuncListGlobalNames(&mut size, &mut names), 0);
+    let names = (0..size as isize)
+      .map(|i| unsafe { CStr::from_ptr(*names.offset(i)).to_str().unwrap() })
+      .collect::<Vec<&str>>();
+    assert!(names.contains(&"test.c_api") && names.contains(&"test.c_api_alias"));
+
+    let missing = CString::new("test.missing").unwrap();
+    assert_eq!(TVMFuncGetGlobal(missing.as_ptr(), &mut handle), 0);
+    assert!(handle.is_null());
+
+    assert_eq!(TVMFuncGetGlobal(ptr::null(), &mut handle), -1);
+    assert_eq!(TVMFuncGetGlobal(name.as_ptr(), ptr::null_mut()), -1);
+    assert_eq!(TVMFuncRegisterGlobal(ptr::null(), handle, 0), -1);
+    assert_eq!(TVMFuncRegisterGlobal(alias.as_ptr(), ptr::null_mut(), 1), -1);
+    assert_eq!(TVMFuncListGlobalNames(ptr::null_mut(), ptr::null_mut()), -1);
+    assert_eq!(TVMFuncFree(ptr::null_mut()), -1);
+  }
+}
@@ -5,7 +5,10 @@ use std::{
 
 use errors::Result;
 use ffi::runtime::TVMValue;
-use runtime::{threading::sgx_join_threads, SystemLibModule, TVMArgValue, TVMRetValue};
+use runtime::{
+  packed_func::call_error_message, set_last_error, threading::sgx_join_threads, SystemLibModule,
+  TVMArgValue, TVMRetValue,
+};
 
 pub use runtime::threading::tvm_run_worker as run_worker;
 
@@ -69,14 +72,17 @@ macro_rules! ocall_packed {
   }
 }
 
-pub fn shutdown() {
+pub fn shutdown() -> Result<()> {
   if env!("TVM_NUM_THREADS") != "0" {
-    sgx_join_threads()
+    sgx_join_threads()?;
   }
+  Ok(())
 }
 
 impl Drop for SystemLibModule {
   fn drop(&mut self) {
-    shutdown()
+    if let Err(err) = shutdown() {
+      set_last_error(call_error_message(err));
+    }
   }
 }
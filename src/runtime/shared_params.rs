@@ -0,0 +1,170 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::{Storage, Tensor};
+use errors::Result;
+
+// Aligns the params such that executors can bind them for dtypes of up to 64 bits.
+// @see `GraphExecutor::storage_align`
+const SHARED_PARAM_ALIGN: usize = 64;
+
+/// A read-only, reference-counted set of params which many `GraphExecutor`s can bind to
+/// without copying, so that each executor only allocates storage for its inputs and
+/// intermediate outputs.
+///
+/// # Examples
+///
+/// ```
+/// let params = SharedParams::new(load_param_dict(&params_bytes).unwrap()).unwrap();
+/// let workers = (0..4).map(|_| {
+///   let (graph, params) = (Graph::try_from(&graph_json).unwrap(), params.clone());
+///   thread::spawn(move || {
+///     let mut exec = GraphExecutor::new_with_shared_params(graph, &SystemLibModule, &params);
+///     // ...
+///   })
+/// });
+/// ```
+#[derive(Clone)]
+pub struct SharedParams {
+  params: Arc<HashMap<String, Tensor<'static>>>,
+}
+
+// The params are never written after construction.
+unsafe impl Send for SharedParams {}
+unsafe impl Sync for SharedParams {}
+
+impl SharedParams {
+  /// Copies `params` into storage which is freed once the last clone of the returned
+  /// `SharedParams` and the last executor bound to it are dropped.
+  pub fn new<'a>(params: HashMap<String, Tensor<'a>>) -> Result<Self> {
+    let mut shared_params = HashMap::new();
+    for (name, param) in params.into_iter() {
+      let num_bytes = param.size * param.dtype.itemsize();
+      let mut shared = Tensor {
+        data: Storage::new(num_bytes, Some(SHARED_PARAM_ALIGN))?,
+        ctx: param.ctx.clone(),
+        dtype: param.dtype.clone(),
+        shape: param.shape.clone(),
+        strides: None,
+        byte_offset: 0,
+        size: param.size,
+      };
+      shared.copy(&param);
+      shared_params.insert(name, shared);
+    }
+    Ok(SharedParams {
+      params: Arc::new(shared_params),
+    })
+  }
+
+  /// Returns the param named `name`, if it exists.
+  pub fn get<S: AsRef<str>>(&self, name: S) -> Option<&Tensor<'static>> {
+    self.params.get(name.as_ref())
+  }
+
+  pub fn len(&self) -> usize {
+    self.params.len()
+  }
+
+  /// Returns views of the params which borrow the shared storage.
+  pub(super) fn views(&self) -> HashMap<String, Tensor<'static>> {
+    self
+      .params
+      .iter()
+      .map(|(name, param)| {
+        (
+          name.clone(),
+          Tensor {
+            data: param.data.view(),
+            ctx: param.ctx.clone(),
+            dtype: param.dtype.clone(),
+            shape: param.shape.clone(),
+            strides: param.strides.clone(),
+            byte_offset: param.byte_offset,
+            size: param.size,
+          },
+        )
+      }).collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{convert::TryFrom, thread};
+
+  use ndarray::Array;
+
+  use super::*;
+  use runtime::{
+    graph::tests::{TestModule, TEST_GRAPH_JSON},
+    Graph, GraphExecutor,
+  };
+
+  fn new_exec(params: &SharedParams) -> GraphExecutor<'static, 'static> {
+    let graph = Graph::try_from(TEST_GRAPH_JSON).unwrap();
+    GraphExecutor::new_with_shared_params(graph, &TestModule, params).unwrap()
+  }
+
+  #[test]
+  fn test_shared_params() {
+    let w = Array::from_shape_vec((2, 2), vec![1f32, 2., 3., 4.]).unwrap();
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), Tensor::from(&w));
+    let params = SharedParams::new(params).unwrap();
+
+    let shared_ptr = params.get("w").unwrap().data.as_ptr();
+    let mut exec1 = new_exec(&params);
+    let mut exec2 = new_exec(&params);
+    assert_eq!(exec1.get_input("w").unwrap().data.as_ptr(), shared_ptr);
+    assert_eq!(exec2.get_input("w").unwrap().data.as_ptr(), shared_ptr);
+    assert_eq!(exec1.params()[0].name, "w");
+
+    let x = Array::from_vec(vec![1f32, 1., 1., 1.]);
+    exec1.set_input("x", Tensor::from(&x)).unwrap();
+    exec1.run().unwrap();
+    assert_eq!(
+      exec1.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 3., 4., 5.]
+    );
+
+    // setting a shared param gives the executor its own copy
+    exec2.set_input("w", Tensor::from(&x)).unwrap();
+    assert!(exec2.get_input("w").unwrap().data.as_ptr() != shared_ptr);
+    exec2.set_input("x", Tensor::from(&x)).unwrap();
+    exec2.run().unwrap();
+    assert_eq!(
+      exec2.get_output(0).unwrap().to_vec::<f32>(),
+      vec![2f32, 2., 2., 2.]
+    );
+    assert_eq!(
+      params.get("w").unwrap().to_vec::<f32>(),
+      vec![1f32, 2., 3., 4.]
+    );
+  }
+
+  #[test]
+  fn test_shared_params_threads() {
+    let w = Array::from_shape_vec((2, 2), vec![1f32, 2., 3., 4.]).unwrap();
+    let mut params = HashMap::new();
+    params.insert("w".to_string(), Tensor::from(&w));
+    let params = SharedParams::new(params).unwrap();
+
+    let workers = (0..4)
+      .map(|i| {
+        let params = params.clone();
+        thread::spawn(move || {
+          let mut exec = new_exec(&params);
+          let x = Array::from_vec(vec![i as f32; 4]);
+          exec.set_input("x", Tensor::from(&x)).unwrap();
+          exec.run().unwrap();
+          exec.get_output(0).unwrap().to_vec::<f32>()
+        })
+      }).collect::<Vec<_>>();
+    for (i, worker) in workers.into_iter().enumerate() {
+      let i = i as f32;
+      assert_eq!(
+        worker.join().unwrap(),
+        vec![i + 1., 2. * i + 1., 3. * i + 1., 4. * i + 1.]
+      );
+    }
+  }
+}
@@ -0,0 +1,224 @@
+use std::cmp;
+
+use super::{DataType, Graph};
+use errors::Result;
+
+/// An assignment of each node output of a `Graph` to a storage.
+///
+/// # Examples
+///
+/// ```
+/// let tvm_plan = StoragePlan::from_graph(&graph).unwrap();
+/// let plan = StoragePlan::new(&graph).unwrap();
+/// println!("{} vs. {} bytes", plan.peak_bytes(), tvm_plan.peak_bytes());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoragePlan {
+  /// The `storage_id` of each node output.
+  pub storage_ids: Vec<usize>,
+  /// The size of each storage.
+  pub storage_num_bytes: Vec<usize>,
+}
+
+impl StoragePlan {
+  /// Plans storage for `graph` using the liveness of its node outputs.
+  ///
+  /// Inputs and params each get their own storage. The output of an op is placed in the smallest
+  /// storage released by a previous op which can hold it, so a graph output may occupy storage
+  /// which held an earlier entry, but no later entry reuses the storage of a graph output.
+  /// Storage is only shared in place by `__nop` ops, whose output occupies the storage of their
+  /// input; other ops never write to the storage of their own inputs.
+  ///
+  /// Only the `shape` and `dltype` attrs of `graph` are required.
+  pub fn new(graph: &Graph) -> Result<Self> {
+    graph.validate()?;
+    let node_row_ptr = graph.node_row_ptr.as_ref().unwrap();
+    let shapes = graph.shapes()?;
+    let dtypes = graph.dtypes()?;
+    let num_entries = node_row_ptr[graph.nodes.len()];
+    ensure!(
+      shapes.len() == num_entries && dtypes.len() == num_entries,
+      "Graph requires `shape` and `dltype` attrs to plan storage."
+    );
+    let entry_num_bytes = (0..num_entries)
+      .map(|eid| entry_num_bytes(&shapes[eid], &dtypes[eid]))
+      .collect::<Vec<usize>>();
+
+    // The number of pending reads of each entry. Entries which must outlive execution are
+    // given an extra read which is never released.
+    let mut ref_counts = vec![0usize; num_entries];
+    for node in graph.nodes.iter() {
+      for entry in node.inputs.iter() {
+        ref_counts[graph.entry_index(entry)?] += 1;
+      }
+    }
+    for entry in graph.heads.iter() {
+      ref_counts[graph.entry_index(entry)?] += 1;
+    }
+    for &id in graph.arg_nodes.iter() {
+      ref_counts[node_row_ptr[id]] += 1;
+    }
+
+    let mut plan = StoragePlan {
+      storage_ids: vec![0; num_entries],
+      storage_num_bytes: Vec::new(),
+    };
+    // The number of pending reads of the entries in each storage.
+    let mut storage_refs: Vec<usize> = Vec::new();
+    let mut free_storages: Vec<usize> = Vec::new();
+    for (i, node) in graph.nodes.iter().enumerate() {
+      let is_nop = node
+        .attrs
+        .as_ref()
+        .and_then(|attrs| attrs.get("func_name"))
+        .map_or(false, |func_name| func_name == "__nop");
+      for eid in node_row_ptr[i]..node_row_ptr[i + 1] {
+        let storage_id = if is_nop && eid == node_row_ptr[i] && node.inputs.len() > 0 {
+          let storage_id = plan.storage_ids[graph.entry_index(&node.inputs[0])?];
+          plan.storage_num_bytes[storage_id] =
+            cmp::max(plan.storage_num_bytes[storage_id], entry_num_bytes[eid]);
+          storage_id
+        } else if node.op == "null" {
+          // args are written before execution, so they cannot reuse storage
+          plan.storage_num_bytes.push(entry_num_bytes[eid]);
+          plan.storage_num_bytes.len() - 1
+        } else {
+          plan.alloc(&mut free_storages, entry_num_bytes[eid])
+        };
+        if storage_id == storage_refs.len() {
+          storage_refs.push(0);
+        }
+        storage_refs[storage_id] += ref_counts[eid];
+        plan.storage_ids[eid] = storage_id;
+      }
+
+      for entry in node.inputs.iter() {
+        let storage_id = plan.storage_ids[graph.entry_index(entry)?];
+        storage_refs[storage_id] -= 1;
+        if storage_refs[storage_id] == 0 {
+          free_storages.push(storage_id);
+        }
+      }
+      // outputs which are never read can be overwritten immediately
+      for eid in node_row_ptr[i]..node_row_ptr[i + 1] {
+        let storage_id = plan.storage_ids[eid];
+        if storage_refs[storage_id] == 0 && !free_storages.contains(&storage_id) {
+          free_storages.push(storage_id);
+        }
+      }
+    }
+    Ok(plan)
+  }
+
+  /// Returns the storage plan given by the `storage_id` attr of `graph`.
+  pub fn from_graph(graph: &Graph) -> Result<Self> {
+    let storage_ids = graph.get_list_attr::<usize>("storage_id")?;
+    let storage_num_bytes = storage_num_bytes(&storage_ids, &graph.shapes()?, &graph.dtypes()?);
+    Ok(StoragePlan {
+      storage_ids: storage_ids,
+      storage_num_bytes: storage_num_bytes,
+    })
+  }
+
+  /// Returns the total size of all storages, which is the memory required to run the graph.
+  pub fn peak_bytes(&self) -> usize {
+    self.storage_num_bytes.iter().sum()
+  }
+
+  /// Returns the id of the smallest free storage which can hold `nbytes`, or of the largest
+  /// free storage, grown to `nbytes`, if none can. A new storage is created if none are free.
+  fn alloc(&mut self, free_storages: &mut Vec<usize>, nbytes: usize) -> usize {
+    let storage_num_bytes = &mut self.storage_num_bytes;
+    let best_fit = free_storages
+      .iter()
+      .enumerate()
+      .min_by_key(|&(_, &storage_id)| {
+        let size = storage_num_bytes[storage_id];
+        (
+          size < nbytes,
+          cmp::max(size, nbytes) - cmp::min(size, nbytes),
+        )
+      }).map(|(i, _)| i);
+    match best_fit {
+      Some(i) => {
+        let storage_id = free_storages.swap_remove(i);
+        storage_num_bytes[storage_id] = cmp::max(storage_num_bytes[storage_id], nbytes);
+        storage_id
+      }
+      None => {
+        storage_num_bytes.push(nbytes);
+        storage_num_bytes.len() - 1
+      }
+    }
+  }
+}
+
+fn entry_num_bytes(shape: &[i64], dtype: &DataType) -> usize {
+  dtype.itemsize() * shape.iter().product::<i64>() as usize
+}
+
+/// Returns the number of bytes required by each `storage_id`.
+pub(super) fn storage_num_bytes(
+  storage_ids: &[usize],
+  shapes: &[Vec<i64>],
+  dtypes: &[DataType],
+) -> Vec<usize> {
+  let mut storage_num_bytes = vec![0usize; *storage_ids.iter().max().unwrap_or(&1) + 1];
+  for (i, &storage_id) in storage_ids.iter().enumerate() {
+    let nbytes = entry_num_bytes(&shapes[i], &dtypes[i]);
+    storage_num_bytes[storage_id] = cmp::max(nbytes, storage_num_bytes[storage_id]);
+  }
+  storage_num_bytes
+}
+
+#[cfg(test)]
+mod tests {
+  use std::convert::TryFrom;
+
+  use ndarray::Array;
+
+  use super::*;
+  use runtime::{
+    graph::tests::{TestModule, TEST_GRAPH_JSON},
+    GraphBuilder, GraphExecutor, Tensor,
+  };
+
+  #[test]
+  fn test_plan_matches_tvm() {
+    let graph = Graph::try_from(TEST_GRAPH_JSON).unwrap();
+    let plan = StoragePlan::new(&graph).unwrap();
+    assert_eq!(plan, StoragePlan::from_graph(&graph).unwrap());
+    assert_eq!(plan.peak_bytes(), 64);
+  }
+
+  #[test]
+  fn test_plan_reuse() {
+    let mut builder = GraphBuilder::new();
+    let x = builder.add_input("x", &[2, 2], "float32").unwrap();
+    let mut out = vec![x];
+    for name in ["a", "b", "c"].iter() {
+      out = builder
+        .add_op(*name, "add_one", &out, &[(&[2, 2], "float32")])
+        .unwrap();
+    }
+    let out = builder
+      .add_op("flatten", "__nop", &out, &[(&[4], "float32")])
+      .unwrap();
+    builder.add_output(out[0]).unwrap();
+    let graph = builder.build().unwrap();
+
+    // `c` reuses the storage of `a`, which `flatten` and so the graph output share in place
+    let plan = StoragePlan::new(&graph).unwrap();
+    assert_eq!(plan.storage_ids, vec![0, 1, 2, 1, 1]);
+    assert_eq!(plan.peak_bytes(), 48);
+    assert_eq!(plan, StoragePlan::from_graph(&graph).unwrap());
+
+    let mut exec = GraphExecutor::new(graph, &TestModule).unwrap();
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    exec.set_input("x", Tensor::from(&x)).unwrap();
+    exec.run().unwrap();
+    let output = exec.get_output(0).unwrap();
+    assert_eq!(output.shape, vec![4]);
+    assert_eq!(output.to_vec::<f32>(), vec![4f32, 5., 6., 7.]);
+  }
+}
@@ -2,7 +2,7 @@ use std::{
   os::raw::{c_int, c_void},
   sync::{
     atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT},
-    Arc, Barrier,
+    Arc, Barrier, Mutex,
   },
 };
 
@@ -15,11 +15,12 @@ use std::{
 };
 
 #[cfg(target_env = "sgx")]
-use std::{collections::VecDeque, ptr, sync::Mutex};
+use std::{collections::VecDeque, ptr};
 
 use bounded_spsc_queue::{self, Producer};
 
 use super::super::errors::*;
+use super::{last_error, packed_func::call_error_message, set_last_error};
 use ffi::runtime::TVMParallelGroupEnv;
 
 #[cfg(target_env = "sgx")]
@@ -34,9 +35,21 @@ struct Job {
   cdata: *const c_void,
   req_num_tasks: usize,
   pending: Arc<AtomicUsize>,
+  /// The error of the first task which failed, if any.
+  error: Arc<Mutex<Option<String>>>,
 }
 
 impl Job {
+  fn new(cb: FTVMParallelLambda, cdata: *const c_void, req_num_tasks: usize) -> Self {
+    Job {
+      cb: cb,
+      cdata: cdata,
+      req_num_tasks: req_num_tasks,
+      pending: Arc::new(ATOMIC_USIZE_INIT),
+      error: Arc::new(Mutex::new(None)),
+    }
+  }
+
   /// Splits this job into a number of `Task`s which can be scheduled.
   fn tasks(&self, num_workers: usize) -> Vec<Task> {
     let num_tasks = if self.req_num_tasks == 0 {
@@ -58,16 +71,21 @@ impl Job {
         },
         cdata: self.cdata,
         pending: Arc::clone(&self.pending),
+        error: Arc::clone(&self.error),
       }).collect()
   }
 
-  /// Waits for all tasks in this `Job` to be completed.
+  /// Waits for all tasks in this `Job` to be completed. Fails with the error of the first task
+  /// which failed, if any.
   fn wait(&self) -> Result<()> {
     while self.pending.load(Ordering::Acquire) > 0 {
       #[cfg(not(target_env = "sgx"))]
       thread::yield_now();
     }
-    Ok(())
+    match self.error.lock().unwrap().take() {
+      Some(msg) => bail!(ErrorKind::FunctionCallError(msg)),
+      None => Ok(()),
+    }
   }
 }
 
@@ -78,6 +96,7 @@ struct Task {
   penv: TVMParallelGroupEnv,
   cdata: *const c_void,
   pending: Arc<AtomicUsize>,
+  error: Arc<Mutex<Option<String>>>,
 }
 unsafe impl Send for Task {}
 unsafe impl Sync for Task {}
@@ -85,7 +104,19 @@ unsafe impl Sync for Task {}
 impl FnOnce<()> for Task {
   type Output = i32;
   extern "rust-call" fn call_once(self, _args: ()) -> Self::Output {
+    // the lambda reports its error on the thread which runs it, so a message left over from an
+    // earlier task must not be mistaken for that of this task
+    set_last_error("");
     let status = (self.flambda)(self.id, &self.penv as *const _, self.cdata);
+    if status != 0 && status != <i32>::min_value() {
+      let msg = match last_error() {
+        ref msg if msg.is_empty() => format!("task {} failed with status {}", self.id, status),
+        msg => msg,
+      };
+      if let Ok(mut error) = self.error.lock() {
+        error.get_or_insert(msg);
+      }
+    }
     self.pending.fetch_sub(1, Ordering::AcqRel);
     status
   }
@@ -151,7 +182,7 @@ impl ThreadPool {
     }
   }
 
-  fn launch(&self, job: Job) {
+  fn launch(&self, job: Job) -> Result<()> {
     let mut tasks = job.tasks(self.num_workers + 1);
 
     for (i, task) in tasks.split_off(1).into_iter().enumerate() {
@@ -159,17 +190,15 @@ impl ThreadPool {
     }
 
     tasks.pop().unwrap()();
-    job.wait().unwrap();
+    job.wait()
   }
 
   fn run_worker(queue: Consumer<Task>) {
     loop {
       let task = queue.pop();
-      let result = task();
-      if result == <i32>::min_value() {
+      // failures are reported to the launching thread through the task's job
+      if task() == <i32>::min_value() {
         break;
-      } else if result != 0 {
-        panic!("Error running task.");
       }
     }
   }
@@ -219,7 +248,7 @@ fn max_concurrency() -> usize {
 }
 
 #[cfg(target_env = "sgx")]
-pub fn tvm_run_worker(_args: &[TVMArgValue]) -> TVMRetValue {
+pub fn tvm_run_worker(_args: &[TVMArgValue]) -> Result<TVMRetValue> {
   let q = {
     let mut qs = SGX_QUEUES.lock().unwrap();
     qs.pop_front()
@@ -228,9 +257,11 @@ pub fn tvm_run_worker(_args: &[TVMArgValue]) -> TVMRetValue {
   if let Some(q) = q {
     ThreadPool::run_worker(q);
   }
-  TVMRetValue::default()
+  Ok(TVMRetValue::default())
 }
 
+/// Runs `cb` as a number of parallel tasks. If a task fails, -1 is returned and the error of
+/// the first task which failed is available from `TVMGetLastError`.
 #[no_mangle]
 pub extern "C" fn TVMBackendParallelLaunch(
   cb: FTVMParallelLambda,
@@ -242,22 +273,21 @@ pub extern "C" fn TVMBackendParallelLaunch(
       sync_handle: 0 as *mut c_void,
       num_task: 1,
     };
-    cb(0, &penv as *const _, cdata);
-  } else {
-    THREAD_POOL.with(|pool| {
-      pool.launch(Job {
-        cb: cb,
-        cdata: cdata,
-        req_num_tasks: num_task,
-        pending: Arc::new(ATOMIC_USIZE_INIT),
-      });
-    });
+    // the lambda sets the last error on this thread if it fails
+    return cb(0, &penv as *const _, cdata);
+  }
+  match THREAD_POOL.with(|pool| pool.launch(Job::new(cb, cdata, num_task))) {
+    Ok(()) => 0,
+    Err(err) => {
+      set_last_error(call_error_message(err));
+      -1
+    }
   }
-  return 0;
 }
 
+/// Stops the workers of this thread's pool. Fails if a worker failed to stop.
 #[cfg(target_env = "sgx")]
-pub(crate) fn sgx_join_threads() {
+pub(crate) fn sgx_join_threads() -> Result<()> {
   extern "C" fn poison_pill(
     _task_id: usize,
     _penv: *const TVMParallelGroupEnv,
@@ -266,15 +296,9 @@ pub(crate) fn sgx_join_threads() {
     <i32>::min_value()
   }
 
-  THREAD_POOL.with(|pool| {
-    pool.launch(Job {
-      cb: poison_pill,
-      cdata: ptr::null(),
-      req_num_tasks: 0,
-      pending: Arc::new(ATOMIC_USIZE_INIT),
-    });
-  });
+  THREAD_POOL.with(|pool| pool.launch(Job::new(poison_pill, ptr::null(), 0)))?;
   ocall_packed!("__sgx_thread_group_join__", 0);
+  Ok(())
 }
 
 // @see https://github.com/dmlc/tvm/issues/988 for information on why this function is used.
@@ -286,9 +310,10 @@ pub extern "C" fn TVMBackendParallelBarrier(_task_id: usize, penv: *const TVMPar
 
 #[cfg(test)]
 mod tests {
-  use std::{ptr, thread, time::Duration};
+  use std::{os::raw::c_char, ptr, thread, time::Duration};
 
   use super::*;
+  use runtime::TVMAPISetLastError;
 
   #[test]
   fn test_max_concurrency() {
@@ -331,4 +356,34 @@ mod tests {
       (0..num_tasks).sum::<usize>()
     );
   }
+
+  extern "C" fn failing_flambda(
+    task_id: usize,
+    _penv: *const TVMParallelGroupEnv,
+    cdata: *const c_void,
+  ) -> i32 {
+    match task_id {
+      0 => 0,
+      _ if cdata.is_null() => -1,
+      _ => {
+        TVMAPISetLastError(cdata as *const c_char);
+        -1
+      }
+    }
+  }
+
+  #[test]
+  fn test_parallel_launch_error() {
+    let msg = b"task failed\0";
+    let status = TVMBackendParallelLaunch(failing_flambda, msg.as_ptr() as *const c_void, 2);
+    assert_eq!(status, -1);
+    assert_eq!(last_error(), "task failed");
+
+    let status = TVMBackendParallelLaunch(failing_flambda, ptr::null(), 2);
+    assert_eq!(status, -1);
+    assert_eq!(last_error(), "task 1 failed with status -1");
+
+    // the workers survive failed tasks
+    assert_eq!(TVMBackendParallelLaunch(flambda, ptr::null(), 2), 0);
+  }
 }
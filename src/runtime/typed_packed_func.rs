@@ -0,0 +1,386 @@
+use std::{convert::TryFrom, ffi::CString, iter::Enumerate, marker::PhantomData, slice};
+
+use super::{PackedFunc, TVMArgValue, TVMRetValue, Tensor};
+use errors::{Error, ErrorKind, Result};
+use ffi::runtime::{DLTensor, TVMTypeCode_kStr, TVMValue};
+
+/// A value which can be passed as an argument of a `TypedPackedFunc`.
+pub trait PackedArg {
+  /// Holds any data to which the `TVMArgValue` refers, such as the `DLTensor` of a `Tensor`.
+  type Holder;
+
+  fn into_holder(self) -> Result<Self::Holder>;
+
+  fn arg_value(holder: &mut Self::Holder) -> TVMArgValue;
+}
+
+macro_rules! impl_prim_packed_arg {
+  ($($type:ty),+) => {
+    $(
+      impl PackedArg for $type {
+        type Holder = $type;
+
+        fn into_holder(self) -> Result<$type> {
+          Ok(self)
+        }
+
+        fn arg_value(holder: &mut $type) -> TVMArgValue {
+          TVMArgValue::from(*holder)
+        }
+      }
+    )+
+  };
+}
+
+impl_prim_packed_arg!(f32, f64, i8, u8, i32, u32, i64, u64, bool);
+
+impl<'a> PackedArg for &'a mut DLTensor {
+  type Holder = &'a mut DLTensor;
+
+  fn into_holder(self) -> Result<Self::Holder> {
+    Ok(self)
+  }
+
+  fn arg_value(holder: &mut Self::Holder) -> TVMArgValue {
+    TVMArgValue::from(&mut **holder)
+  }
+}
+
+impl<'a, 't> PackedArg for &'a Tensor<'t> {
+  type Holder = DLTensor;
+
+  fn into_holder(self) -> Result<DLTensor> {
+    Ok(DLTensor::from(self))
+  }
+
+  fn arg_value(holder: &mut DLTensor) -> TVMArgValue {
+    TVMArgValue::from(&mut *holder)
+  }
+}
+
+impl<'a, 't> PackedArg for &'a mut Tensor<'t> {
+  type Holder = DLTensor;
+
+  fn into_holder(self) -> Result<DLTensor> {
+    Ok(DLTensor::from(self))
+  }
+
+  fn arg_value(holder: &mut DLTensor) -> TVMArgValue {
+    TVMArgValue::from(&mut *holder)
+  }
+}
+
+impl<'a> PackedArg for &'a str {
+  type Holder = CString;
+
+  fn into_holder(self) -> Result<CString> {
+    Ok(CString::new(self).map_err(|_| format!("String argument `{}` contains a nul", self))?)
+  }
+
+  fn arg_value(holder: &mut CString) -> TVMArgValue {
+    TVMArgValue::new(
+      TVMValue {
+        v_str: holder.as_ptr(),
+      },
+      TVMTypeCode_kStr as i64,
+    )
+  }
+}
+
+impl PackedArg for String {
+  type Holder = CString;
+
+  fn into_holder(self) -> Result<CString> {
+    self.as_str().into_holder()
+  }
+
+  fn arg_value(holder: &mut CString) -> TVMArgValue {
+    <&str as PackedArg>::arg_value(holder)
+  }
+}
+
+/// A tuple of `PackedArg`s with which a `TypedPackedFunc` can be called.
+pub trait PackedArgs {
+  /// Calls `func` with these arguments.
+  fn call_packed(self, func: &PackedFunc) -> Result<TVMRetValue>;
+}
+
+macro_rules! impl_packed_args {
+  ($($arg:ident),*) => {
+    impl<$($arg: PackedArg),*> PackedArgs for ($($arg,)*) {
+      #[allow(non_snake_case, unused_mut)]
+      fn call_packed(self, func: &PackedFunc) -> Result<TVMRetValue> {
+        let ($($arg,)*) = self;
+        $(let mut $arg = $arg.into_holder()?;)*
+        func(&[$(<$arg as PackedArg>::arg_value(&mut $arg)),*])
+      }
+    }
+  };
+}
+
+impl_packed_args!();
+impl_packed_args!(A);
+impl_packed_args!(A, B);
+impl_packed_args!(A, B, C);
+impl_packed_args!(A, B, C, D);
+impl_packed_args!(A, B, C, D, E);
+impl_packed_args!(A, B, C, D, E, F);
+
+/// A `PackedFunc` which is called with a tuple of Rust values and whose return value is
+/// converted to `Ret`.
+///
+/// # Examples
+///
+/// ```
+/// let add: TypedPackedFunc<(&Tensor, &Tensor, &mut Tensor), ()> =
+///   TypedPackedFunc::new(lib.get_function("default_function").unwrap());
+/// add.call((&a, &b, &mut c)).unwrap();
+///
+/// let answer: TypedPackedFunc<(), i64> = TypedPackedFunc::new(get_global("answer").unwrap());
+/// assert_eq!(answer.call(()).unwrap(), 42);
+/// ```
+pub struct TypedPackedFunc<Args, Ret> {
+  func: PackedFunc,
+  _signature: PhantomData<fn(Args) -> Ret>,
+}
+
+impl<Args, Ret> TypedPackedFunc<Args, Ret>
+where
+  Args: PackedArgs,
+  Ret: TryFrom<TVMRetValue, Error = Error>,
+{
+  pub fn new(func: PackedFunc) -> Self {
+    TypedPackedFunc {
+      func: func,
+      _signature: PhantomData,
+    }
+  }
+
+  /// Calls the function with `args` and converts its return value to `Ret`.
+  pub fn call(&self, args: Args) -> Result<Ret> {
+    Ret::try_from(args.call_packed(&self.func)?)
+  }
+
+  pub fn into_packed_func(self) -> PackedFunc {
+    self.func
+  }
+}
+
+/// Marks a `&Tensor` parameter in the `Args` of `IntoPackedFunc`.
+pub struct TensorRef;
+
+/// A Rust function which can be wrapped as a `PackedFunc`. The function can have up to four
+/// parameters, each of which is either a `&Tensor` or a type, such as `i64`, `f32`, or
+/// `String`, which can be converted from a `TVMArgValue`. `Args` has an element for each
+/// parameter, which is `TensorRef` for a `&Tensor`. A `&Tensor` parameter refers to a copy of
+/// the data of its argument.
+///
+/// The `PackedFunc` fails if it is called with the wrong number of arguments or with an
+/// argument which cannot be converted to the type of its parameter.
+///
+/// # Examples
+///
+/// ```
+/// fn scaled_sum(scale: i64, tensor: &Tensor) -> f32 {
+///   scale as f32 * tensor.to_vec::<f32>().iter().sum::<f32>()
+/// }
+///
+/// register_global("scaled_sum", scaled_sum.into_packed_func(), false).unwrap();
+/// ```
+pub trait IntoPackedFunc<Args, Ret> {
+  fn into_packed_func(self) -> PackedFunc;
+}
+
+/// Converts the next of the `args` of a packed function implemented in Rust.
+fn next_arg<'a, 'b, T>(args: &mut Enumerate<slice::Iter<'b, TVMArgValue<'a>>>) -> Result<T>
+where
+  T: TryFrom<TVMArgValue<'a>, Error = Error>,
+{
+  let (i, &arg) = args.next().unwrap();
+  T::try_from(arg).map_err(|err| arg_error(i, err))
+}
+
+/// Converts the next of the `args` of a packed function implemented in Rust to a `Tensor`.
+fn next_tensor_arg<'a, 'b>(
+  args: &mut Enumerate<slice::Iter<'b, TVMArgValue<'a>>>,
+) -> Result<Tensor<'static>> {
+  let (i, &arg) = args.next().unwrap();
+  // callers of a `PackedFunc` pass array handles only for valid `DLTensor`s, as for functions
+  // loaded from a library
+  unsafe { Tensor::from_arg(arg) }.map_err(|err| arg_error(i, err))
+}
+
+fn arg_error(i: usize, err: Error) -> Error {
+  ErrorKind::FunctionCallError(format!("could not convert argument {}: {}", i, err)).into()
+}
+
+/// Implements `IntoPackedFunc` for functions of the given parameters in each combination of
+/// `&Tensor` and converted parameters.
+macro_rules! impl_into_packed_func {
+  ($($arg:ident)*) => {
+    impl_into_packed_func!(@combine [] [] [] [] [$($arg)*]);
+  };
+  (
+    @combine [$($gen:tt)*] [$($marker:ty),*] [$($param:ty),*] [$($kind:ident $name:ident),*]
+    [$next:ident $($rest:ident)*]
+  ) => {
+    impl_into_packed_func!(
+      @combine
+      [$($gen)* $next: for<'a> TryFrom<TVMArgValue<'a>, Error = Error>,]
+      [$($marker,)* $next]
+      [$($param,)* $next]
+      [$($kind $name,)* value $next]
+      [$($rest)*]
+    );
+    impl_into_packed_func!(
+      @combine
+      [$($gen)*]
+      [$($marker,)* TensorRef]
+      [$($param,)* &Tensor]
+      [$($kind $name,)* tensor $next]
+      [$($rest)*]
+    );
+  };
+  (@combine [$($gen:tt)*] [$($marker:ty),*] [$($param:ty),*] [$($kind:ident $name:ident),*] []) => {
+    impl<Func, Ret, $($gen)*> IntoPackedFunc<($($marker,)*), Ret> for Func
+    where
+      Func: Fn($($param),*) -> Ret + Send + Sync + 'static,
+      Ret: Into<TVMRetValue>,
+    {
+      #[allow(non_snake_case, unused_mut, unused_variables)]
+      fn into_packed_func(self) -> PackedFunc {
+        box move |args: &[TVMArgValue]| {
+          let names: &[&str] = &[$(stringify!($name)),*];
+          ensure!(
+            args.len() == names.len(),
+            ErrorKind::FunctionCallError(format!(
+              "expected {} arguments but got {}",
+              names.len(),
+              args.len()
+            ))
+          );
+          let mut args = args.iter().enumerate();
+          $(let $name = impl_into_packed_func!(@convert $kind args)?;)*
+          Ok(self($(impl_into_packed_func!(@pass $kind $name)),*).into())
+        }
+      }
+    }
+  };
+  (@convert value $args:ident) => {
+    next_arg(&mut $args)
+  };
+  (@convert tensor $args:ident) => {
+    next_tensor_arg(&mut $args)
+  };
+  (@pass value $name:ident) => {
+    $name
+  };
+  (@pass tensor $name:ident) => {
+    &$name
+  };
+}
+
+impl_into_packed_func!();
+impl_into_packed_func!(A);
+impl_into_packed_func!(A B);
+impl_into_packed_func!(A B C);
+impl_into_packed_func!(A B C D);
+
+#[cfg(test)]
+mod tests {
+  use std::ptr;
+
+  use ndarray::Array;
+
+  use super::*;
+  use runtime::{graph::tests::TestModule, FuncModule, Module};
+
+  fn scaled_sum(scale: i64, tensor: &Tensor) -> f32 {
+    scale as f32 * tensor.to_vec::<f32>().iter().sum::<f32>()
+  }
+
+  #[test]
+  fn test_typed_packed_func() {
+    let sum: TypedPackedFunc<(i64, &Tensor), f32> =
+      TypedPackedFunc::new(scaled_sum.into_packed_func());
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    let x = Tensor::from(&x);
+    assert_eq!(sum.call((2, &x)).unwrap(), 20.);
+
+    let concat: TypedPackedFunc<(&str, String), String> =
+      TypedPackedFunc::new((|a: String, b: String| a + &b).into_packed_func());
+    assert_eq!(
+      concat.call(("packed", "func".to_string())).unwrap(),
+      "packedfunc"
+    );
+
+    let add_one: TypedPackedFunc<(&Tensor, &mut Tensor), ()> =
+      TypedPackedFunc::new(TestModule.get_function("add_one").unwrap());
+    let out = Array::from_vec(vec![0f32; 4]);
+    let mut out = Tensor::from(&out);
+    add_one.call((&x, &mut out)).unwrap();
+    assert_eq!(out.to_vec::<f32>(), vec![2f32, 3., 4., 5.]);
+  }
+
+  #[test]
+  fn test_into_packed_func_errors() {
+    let mut funcs = FuncModule::new();
+    funcs.register("scaled_sum", scaled_sum.into_packed_func());
+    let sum = funcs.get_function("scaled_sum").unwrap();
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    let x = Tensor::from(&x);
+
+    let err = TypedPackedFunc::<(i64,), f32>::new(funcs.get_function("scaled_sum").unwrap())
+      .call((2,))
+      .err()
+      .unwrap();
+    assert!(err.to_string().contains("expected 2 arguments but got 1"));
+
+    let err = TypedPackedFunc::<(f64, &Tensor), f32>::new(sum)
+      .call((2., &x))
+      .err()
+      .unwrap();
+    assert!(err.to_string().contains("could not convert argument 0"));
+
+    let unit: TypedPackedFunc<(), i64> = TypedPackedFunc::new((|| ()).into_packed_func());
+    assert!(unit.call(()).is_err());
+  }
+
+  #[test]
+  fn test_into_packed_func_tensor_arg() {
+    let sum = scaled_sum.into_packed_func();
+    let x = Array::from_vec(vec![1f32, 2., 3., 4.]);
+    let x = Tensor::from(&x);
+    let mut dl_tensor = DLTensor::from(&x);
+    let ret = sum(&[TVMArgValue::from(1i64), TVMArgValue::from(&mut dl_tensor)]).unwrap();
+    assert_eq!(f32::try_from(ret).unwrap(), 10.);
+
+    let mut strides = vec![-1i64];
+    dl_tensor.strides = strides.as_mut_ptr();
+    let err = sum(&[TVMArgValue::from(1i64), TVMArgValue::from(&mut dl_tensor)])
+      .err()
+      .unwrap();
+    assert!(err.to_string().contains("Negative strides"), "{}", err);
+
+    dl_tensor.strides = ptr::null_mut();
+    dl_tensor.data = ptr::null_mut();
+    let err = sum(&[TVMArgValue::from(1i64), TVMArgValue::from(&mut dl_tensor)])
+      .err()
+      .unwrap();
+    assert!(err.to_string().contains("data of a tensor is null"), "{}", err);
+    dl_tensor.shape = ptr::null_mut();
+    let err = sum(&[TVMArgValue::from(1i64), TVMArgValue::from(&mut dl_tensor)])
+      .err()
+      .unwrap();
+    assert!(err.to_string().contains("shape of a tensor"), "{}", err);
+
+    // frontends pass scalars with a null shape
+    let scalar = Array::from_vec(vec![5f32]);
+    let scalar = Tensor::from(&scalar);
+    let mut dl_scalar = DLTensor::from(&scalar);
+    dl_scalar.ndim = 0;
+    dl_scalar.shape = ptr::null_mut();
+    let ret = sum(&[TVMArgValue::from(2i64), TVMArgValue::from(&mut dl_scalar)]).unwrap();
+    assert_eq!(f32::try_from(ret).unwrap(), 10.);
+  }
+}
@@ -9,7 +9,7 @@ extern crate tvm;
 use std::{collections::HashMap, convert::TryFrom, fs, io::Read};
 
 use ndarray::Array;
-use tvm::runtime::{Graph, GraphExecutor, SystemLibModule, Tensor};
+use tvm::runtime::{Graph, GraphExecutor, ParamCheck, SystemLibModule, Tensor};
 
 const BATCH_SIZE: usize = 4;
 const IN_DIM: usize = 8;
@@ -65,14 +65,14 @@ fn main() {
   let expected_o0 = &left + 1f32;
   let expected_o1 = &right - 1f32;
 
-  exec.load_params(params);
-  exec.set_input("data", x.clone().into());
+  exec.load_params(params, ParamCheck::Strict).unwrap();
+  exec.set_input("data", x.clone().into()).unwrap();
 
   check_sum!(exec, data, x);
   check_sum!(exec, dense0_weight, w);
   check_sum!(exec, dense0_bias, b);
 
-  exec.run();
+  exec.run().unwrap();
 
   check_sum!(exec, 0, expected_o0);
   check_sum!(exec, 1, expected_o1);
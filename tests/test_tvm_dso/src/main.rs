@@ -0,0 +1,26 @@
+extern crate ndarray;
+#[macro_use]
+extern crate tvm;
+
+use ndarray::Array;
+use tvm::{
+  ffi::runtime::DLTensor,
+  runtime::{DsoModule, Module},
+};
+
+fn main() {
+  let lib = DsoModule::new(concat!(env!("OUT_DIR"), "/test.so")).unwrap();
+  assert_eq!(lib.entry_name(), Some("add".to_string()));
+  let add = lib
+    .get_function("__tvm_main__")
+    .expect("main function not found");
+  let mut a = Array::from_vec(vec![1f32, 2., 3., 4.]);
+  let mut b = Array::from_vec(vec![1f32, 0., 1., 0.]);
+  let mut c = Array::from_vec(vec![0f32; 4]);
+  let e = Array::from_vec(vec![2f32, 2., 4., 4.]);
+  let mut a_dl: DLTensor = (&mut a).into();
+  let mut b_dl: DLTensor = (&mut b).into();
+  let mut c_dl: DLTensor = (&mut c).into();
+  call_packed!(add, &mut a_dl, &mut b_dl, &mut c_dl).unwrap();
+  assert!(c.all_close(&e, 1e-8f32));
+}